@@ -0,0 +1,77 @@
+//! Acting on sessions: jumping to an agent's pane and stopping agents.
+
+use crate::backend::{Multiplexer, Screen, Tmux, Zellij};
+use crate::error::{run_command, Error, Result};
+use crate::session::{AiSession, MultiplexerKind};
+use crate::tmux::{record_visit, ServerPane};
+
+/// Bring the session's pane into view.
+///
+/// Inside tmux this switches the current client to the pane. Outside tmux it
+/// replaces the current process with `tmux attach-session`, so it only returns
+/// on failure. With `zoom`, the pane is zoomed unless its window already is.
+/// zellij panes are focused in their session, which is attached the same way;
+/// screen windows are selected when their session is attached and attached
+/// with `screen -r` otherwise.
+pub fn jump_to_session(session: &AiSession, zoom: bool) -> Result<()> {
+    jump(session, zoom, false)
+}
+
+/// [`jump_to_session`], also recording the jump in the list of visited panes
+/// `rpai back` uses, in `~/.config/rpai/mru.json`.
+pub fn jump_to_session_recording_visit(session: &AiSession, zoom: bool) -> Result<()> {
+    jump(session, zoom, true)
+}
+
+fn jump(session: &AiSession, zoom: bool, record: bool) -> Result<()> {
+    match session.multiplexer {
+        Some(MultiplexerKind::Zellij) => jump_with(&Zellij, session, zoom, record),
+        Some(MultiplexerKind::Screen) => jump_with(&Screen, session, zoom, record),
+        _ => jump_with(&Tmux::default(), session, zoom, record),
+    }
+}
+
+/// [`jump_to_session`] through an explicit multiplexer.
+pub fn jump_to_session_with(
+    multiplexer: &dyn Multiplexer,
+    session: &AiSession,
+    zoom: bool,
+) -> Result<()> {
+    jump_with(multiplexer, session, zoom, false)
+}
+
+fn jump_with(
+    multiplexer: &dyn Multiplexer,
+    session: &AiSession,
+    zoom: bool,
+    record: bool,
+) -> Result<()> {
+    let Some(target) = ServerPane::of(session) else {
+        return Err(Error::NotInMultiplexer);
+    };
+
+    // The pane rpai runs in is on the server plain commands reach
+    let origin = multiplexer
+        .current_pane()
+        .map(|pane| ServerPane::new(None, &pane));
+    multiplexer.focus(session, zoom)?;
+    if record {
+        record_visit(origin.as_ref(), &target);
+    }
+    multiplexer.attach(session)
+}
+
+/// Terminate a session's agent process with SIGTERM.
+pub fn kill_session(session: &AiSession) -> Result<()> {
+    kill_pid(session.pid)
+}
+
+/// Send SIGTERM to a process.
+pub fn kill_pid(pid: u32) -> Result<()> {
+    run_command("kill", &[&pid.to_string()]).map(|_| ())
+}
+
+/// Send a named signal (e.g. "TERM", "INT") to a process.
+pub fn signal_pid(pid: u32, signal: &str) -> Result<()> {
+    run_command("kill", &["-s", signal, &pid.to_string()]).map(|_| ())
+}
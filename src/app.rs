@@ -0,0 +1,1521 @@
+//! TUI state, input handling and the event loop.
+
+use crate::commands::{
+    complete_path, config_keys, expand_tilde, load_command_history, save_command_history,
+    set_config_value, Completion, AGENT_COMMANDS, COMMANDS,
+};
+use crate::grouping::{
+    group_sessions, sort_sessions, GitRoots, GroupBy, ListRow, SessionGroup, SortBy,
+};
+use crate::keys::{Action, KeyBindings};
+use crate::layout::{ColumnData, ListLayout};
+use crate::panes::{
+    copy_to_clipboard, in_tmux_pane, new_agent_window, notify, open_peek_popup, peek_pane,
+    peek_title, pull_pane, push_pane, run_tmux_on, send_to_pane, PushTarget,
+};
+use crate::reaper::{reap_idle_sessions, REAP_INTERVAL_SECS};
+use crate::targets::resolve_session;
+use crate::theme::{load_theme, save_theme, ColorSupport, Theme, ThemeSet};
+use crate::ui::{memory_warning, ui};
+use crate::Result;
+use crossterm::{
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, MouseEventKind,
+    },
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{backend::CrosstermBackend, widgets::ListState, Terminal};
+use rpai::support::{
+    current_tmux_pane, kill_pid, previous_visited_pane, save_session_name, ServerPane,
+};
+use rpai::{AiSession, Config, ListeningPort, Scanner, SessionDetails};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::io::{stdout, Stdout};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum AppMode {
+    Normal,
+    Command,
+    /// Waiting for y/n on `App::confirm`
+    Confirm,
+}
+
+/// A destructive action that runs only once the user presses y.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Confirm {
+    /// Stop an agent
+    Kill { pid: u32, id: String, agent: String },
+    /// Stop a server listening in an agent's process tree
+    KillServer { agent_pid: u32, port: ListeningPort },
+}
+
+impl Confirm {
+    pub(crate) fn prompt(&self) -> String {
+        match self {
+            Confirm::Kill { pid, id, agent } => {
+                format!("Kill {} {} (PID: {})? y/N", agent, id, pid)
+            }
+            Confirm::KillServer { port, .. } => {
+                format!("Kill {} (PID: {})? y/N", port.label(), port.pid)
+            }
+        }
+    }
+}
+
+/// Shortest accepted `refresh_ms`: each refresh rescans processes, and 0 would
+/// turn the event loop into a busy wait.
+const MIN_REFRESH_MS: u64 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum View {
+    List,
+    /// Tiled grid of every visible session's live pane output
+    Dashboard,
+    /// Full metadata and process tree of one session
+    Detail,
+}
+
+/// Minimum interval between `capture-pane` rounds in the dashboard.
+const DASHBOARD_CAPTURE_MS: u64 = 500;
+
+/// Narrowest dashboard cell before fewer columns are used.
+pub(crate) const DASHBOARD_MIN_CELL_WIDTH: u16 = 30;
+
+pub(crate) struct App {
+    pub(crate) sessions: Vec<AiSession>,
+    pub(crate) groups: Vec<SessionGroup>,
+    pub(crate) rows: Vec<ListRow>,
+    pub(crate) collapsed: HashSet<String>,
+    pub(crate) group_by: GroupBy,
+    git_roots: GitRoots,
+    pub(crate) list_state: ListState,
+    should_quit: bool,
+    selected_session: Option<usize>,
+    pub(crate) theme_name: String,
+    pub(crate) theme: Theme,
+    pub(crate) themes: ThemeSet,
+    pub(crate) color_support: ColorSupport,
+    pub(crate) layout: ListLayout,
+    pub(crate) mode: AppMode,
+    /// Action awaiting confirmation in `AppMode::Confirm`
+    pub(crate) confirm: Option<Confirm>,
+    pub(crate) command_input: String,
+    pub(crate) status_message: Option<String>,
+    pub(crate) status_is_error: bool,
+    pub(crate) filter: Option<String>,
+    pub(crate) sort_by: SortBy,
+    pub(crate) history: Vec<String>,
+    history_idx: Option<usize>,
+    pub(crate) completion: Option<Completion>,
+    pub(crate) view: View,
+    /// PID of the highlighted dashboard cell's agent, so the highlight stays
+    /// on it when a refresh reorders the sessions
+    pub(crate) dashboard_pid: Option<u32>,
+    /// Latest captured pane contents by agent PID
+    pub(crate) pane_captures: HashMap<u32, Vec<String>>,
+    last_capture: Option<Instant>,
+    /// Grid width of the last rendered dashboard, for arrow-key movement
+    pub(crate) dashboard_cols: usize,
+    /// View to return to when the detail view is closed
+    detail_return: View,
+    detail_pid: Option<u32>,
+    pub(crate) details: Option<SessionDetails>,
+    pub(crate) detail_scroll: u16,
+    /// Session to peek at, handled by the event loop which owns the terminal
+    peek_request: Option<usize>,
+    last_reap: Option<Instant>,
+    /// Sessions already warned about for exceeding `memory_warning_mb`
+    memory_warned: HashSet<u32>,
+    /// Most recently visited agent pane other than the one rpai was opened from
+    pub(crate) previous_pane: Option<ServerPane>,
+    pub(crate) column_data: ColumnData,
+    last_refresh: Instant,
+    /// Kept across refreshes so the detail view can reuse the last scan
+    scanner: Scanner,
+    pub(crate) config: Config,
+    pub(crate) bindings: KeyBindings,
+}
+
+impl App {
+    pub(crate) fn new(sessions: Vec<AiSession>, bindings: KeyBindings) -> Self {
+        let mut app = Self::with_config(sessions, bindings, Config::load(), ThemeSet::load());
+        app.history = load_command_history();
+        app.previous_pane = previous_visited_pane(
+            current_tmux_pane()
+                .map(|p| ServerPane::new(None, &p))
+                .as_ref(),
+        );
+        app.column_data.refresh(&app.sessions, &app.layout);
+        app.check_memory_warnings();
+        app
+    }
+
+    /// An app for `config` that reads nothing from disk or tmux.
+    fn with_config(
+        mut sessions: Vec<AiSession>,
+        bindings: KeyBindings,
+        config: Config,
+        themes: ThemeSet,
+    ) -> Self {
+        let (theme_name, theme) = load_theme(&themes, &config);
+        let group_by = GroupBy::parse(&config.group_by).unwrap_or(GroupBy::None);
+        let color_support = ColorSupport::resolve(&config.color);
+        let sort_by = SortBy::parse(&config.sort_by).unwrap_or(SortBy::Agent);
+        sort_sessions(&mut sessions, sort_by);
+        let (layout, layout_error) = match ListLayout::from_config(&config) {
+            Ok(layout) => (layout, None),
+            Err(e) => (ListLayout::Normal, Some(format!("Invalid layout: {}", e))),
+        };
+        // Only the first problem fits the status line; `rpai theme` lists them all
+        let theme_error = themes.errors.first().map(|e| match themes.errors.len() {
+            1 => format!("Skipping custom theme {}", e),
+            n => format!("Skipping custom theme {} (and {} more)", e, n - 1),
+        });
+        let startup_error = layout_error.or(theme_error);
+        let scanner = Scanner::new(config.clone()).with_saved_state();
+        let mut app = Self {
+            sessions,
+            groups: Vec::new(),
+            rows: Vec::new(),
+            collapsed: HashSet::new(),
+            group_by,
+            git_roots: GitRoots::new(),
+            list_state: ListState::default(),
+            should_quit: false,
+            selected_session: None,
+            theme_name,
+            theme,
+            themes,
+            color_support,
+            layout,
+            mode: AppMode::Normal,
+            confirm: None,
+            command_input: String::new(),
+            status_is_error: startup_error.is_some(),
+            status_message: startup_error,
+            filter: None,
+            sort_by,
+            history: Vec::new(),
+            history_idx: None,
+            completion: None,
+            view: View::List,
+            dashboard_pid: None,
+            detail_return: View::List,
+            detail_pid: None,
+            details: None,
+            detail_scroll: 0,
+            memory_warned: HashSet::new(),
+            last_reap: None,
+            pane_captures: HashMap::new(),
+            last_capture: None,
+            dashboard_cols: 1,
+            peek_request: None,
+            previous_pane: None,
+            column_data: ColumnData::default(),
+            last_refresh: Instant::now(),
+            scanner,
+            config,
+            bindings,
+        };
+        app.rebuild_rows();
+        if !app.rows.is_empty() {
+            app.list_state.select(Some(0));
+        }
+        app
+    }
+
+    /// Rebuild the visible rows from sessions, grouping and collapse state,
+    /// keeping the selection on the same session (by PID) or group header.
+    fn rebuild_rows(&mut self) {
+        let (selected_pid, selected_group) = self.selection_key();
+        self.rebuild_rows_with(selected_pid, selected_group);
+    }
+
+    fn selection_key(&self) -> (Option<u32>, Option<String>) {
+        let selected_group = match self.selected_row() {
+            Some(ListRow::Group(g)) => self.groups.get(g).map(|g| g.key.clone()),
+            _ => None,
+        };
+        (self.selected_pid(), selected_group)
+    }
+
+    fn rebuild_rows_with(&mut self, selected_pid: Option<u32>, selected_group: Option<String>) {
+        let visible: Vec<usize> = (0..self.sessions.len())
+            .filter(|&i| self.matches_filter(&self.sessions[i]))
+            .collect();
+
+        self.rows.clear();
+        if self.group_by == GroupBy::None {
+            self.groups.clear();
+            self.rows = visible.into_iter().map(ListRow::Session).collect();
+        } else {
+            self.groups =
+                group_sessions(&self.sessions, &visible, self.group_by, &mut self.git_roots);
+            for (g, group) in self.groups.iter().enumerate() {
+                self.rows.push(ListRow::Group(g));
+                if !self.collapsed.contains(&group.key) {
+                    self.rows
+                        .extend(group.sessions.iter().map(|&i| ListRow::Session(i)));
+                }
+            }
+        }
+
+        let restored = if let Some(pid) = selected_pid {
+            self.sessions
+                .iter()
+                .position(|s| s.pid == pid)
+                .and_then(|i| {
+                    self.rows
+                        .iter()
+                        .position(|r| *r == ListRow::Session(i))
+                        // Session is hidden inside a collapsed group: select its header
+                        .or_else(|| {
+                            let g = self.groups.iter().position(|g| g.sessions.contains(&i))?;
+                            self.rows.iter().position(|r| *r == ListRow::Group(g))
+                        })
+                })
+        } else if let Some(key) = selected_group {
+            self.groups
+                .iter()
+                .position(|g| g.key == key)
+                .and_then(|g| self.rows.iter().position(|r| *r == ListRow::Group(g)))
+        } else {
+            None
+        };
+
+        let fallback = self
+            .list_state
+            .selected()
+            .map(|i| i.min(self.rows.len().saturating_sub(1)));
+        if self.rows.is_empty() {
+            self.list_state.select(None);
+        } else {
+            self.list_state.select(restored.or(fallback));
+        }
+    }
+
+    fn set_sessions(&mut self, mut sessions: Vec<AiSession>) {
+        let (selected_pid, selected_group) = self.selection_key();
+        sort_sessions(&mut sessions, self.sort_by);
+        self.sessions = sessions;
+        self.rebuild_rows_with(selected_pid, selected_group);
+        self.column_data.refresh(&self.sessions, &self.layout);
+        self.check_memory_warnings();
+    }
+
+    /// Run a reaper pass at most every `REAP_INTERVAL_SECS`, when enabled.
+    fn run_reaper(&mut self) {
+        if !self.config.reaper.enabled
+            || self
+                .last_reap
+                .is_some_and(|t| t.elapsed() < Duration::from_secs(REAP_INTERVAL_SECS))
+        {
+            return;
+        }
+        self.last_reap = Some(Instant::now());
+        match reap_idle_sessions(
+            &self.sessions,
+            &self.config.reaper,
+            self.config.reaper.dry_run,
+        ) {
+            Ok(messages) => {
+                if let Some(last) = messages.last() {
+                    self.info(format!("Reaper: {}", last));
+                }
+            }
+            Err(e) => self.error(format!("Reaper: {}", e)),
+        }
+    }
+
+    /// Notify once each time a session crosses `memory_warning_mb`.
+    fn check_memory_warnings(&mut self) {
+        let over: Vec<usize> = (0..self.sessions.len())
+            .filter(|&i| memory_warning(&self.sessions[i], &self.config))
+            .collect();
+        self.memory_warned
+            .retain(|pid| over.iter().any(|&i| self.sessions[i].pid == *pid));
+        for i in over {
+            let session = &self.sessions[i];
+            if self.memory_warned.insert(session.pid) {
+                let msg = format!(
+                    "{} {} is using {}MB (warning at {}MB)",
+                    session.agent_type,
+                    session.id,
+                    session.memory_mb,
+                    self.config.memory_warning_mb
+                );
+                notify("rpai", &msg);
+                self.error(msg);
+            }
+        }
+    }
+
+    pub(crate) fn selected_row(&self) -> Option<ListRow> {
+        self.list_state
+            .selected()
+            .and_then(|i| self.rows.get(i).copied())
+    }
+
+    fn selected_session_index(&self) -> Option<usize> {
+        match self.selected_row() {
+            Some(ListRow::Session(i)) => Some(i),
+            _ => None,
+        }
+    }
+
+    fn selected_pid(&self) -> Option<u32> {
+        self.selected_session_index()
+            .and_then(|i| self.sessions.get(i))
+            .map(|s| s.pid)
+    }
+
+    /// Height in terminal lines of a rendered row.
+    fn row_height(&self, row: ListRow) -> usize {
+        match row {
+            ListRow::Group(_) => 1,
+            ListRow::Session(_) => self.layout.lines_per_item(),
+        }
+    }
+
+    /// Map a line offset within the list area to a row index, accounting for scrolling.
+    fn row_at(&self, line: usize) -> Option<usize> {
+        let mut top = 0;
+        for (i, &row) in self.rows.iter().enumerate().skip(self.list_state.offset()) {
+            let height = self.row_height(row);
+            if line < top + height {
+                return Some(i);
+            }
+            top += height;
+        }
+        None
+    }
+
+    pub(crate) fn next(&mut self) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let i = match self.list_state.selected() {
+            Some(i) => {
+                if i >= self.rows.len() - 1 {
+                    0
+                } else {
+                    i + 1
+                }
+            }
+            None => 0,
+        };
+        self.list_state.select(Some(i));
+    }
+
+    pub(crate) fn previous(&mut self) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let i = match self.list_state.selected() {
+            Some(i) => {
+                if i == 0 {
+                    self.rows.len() - 1
+                } else {
+                    i - 1
+                }
+            }
+            None => 0,
+        };
+        self.list_state.select(Some(i));
+    }
+
+    pub(crate) fn select(&mut self) {
+        if self.view == View::Detail {
+            if let Some(i) = self.detail_session_index() {
+                self.selected_session = Some(i);
+                self.should_quit = true;
+            }
+            return;
+        }
+        if self.view == View::Dashboard {
+            if let Some(i) = self.focused_session_index() {
+                self.selected_session = Some(i);
+                self.should_quit = true;
+            }
+            return;
+        }
+        match self.selected_row() {
+            Some(ListRow::Group(g)) => self.toggle_group(g),
+            Some(ListRow::Session(i)) => {
+                self.selected_session = Some(i);
+                self.should_quit = true;
+            }
+            None => {}
+        }
+    }
+
+    fn toggle_group(&mut self, g: usize) {
+        if let Some(group) = self.groups.get(g) {
+            if !self.collapsed.remove(&group.key) {
+                self.collapsed.insert(group.key.clone());
+            }
+            self.rebuild_rows();
+        }
+    }
+
+    /// Collapse or expand the group the current selection belongs to.
+    fn toggle_selected_group(&mut self) {
+        let group = match self.selected_row() {
+            Some(ListRow::Group(g)) => Some(g),
+            Some(ListRow::Session(i)) => self.groups.iter().position(|g| g.sessions.contains(&i)),
+            None => None,
+        };
+        if let Some(g) = group {
+            // Move the selection onto the header so it stays visible after collapsing
+            if let Some(row) = self.rows.iter().position(|r| *r == ListRow::Group(g)) {
+                self.list_state.select(Some(row));
+            }
+            self.toggle_group(g);
+        }
+    }
+
+    fn set_group_by(&mut self, group_by: GroupBy) {
+        self.group_by = group_by;
+        self.rebuild_rows();
+        self.config.group_by = group_by.name().to_string();
+        let _ = self.config.save();
+        self.info(format!("Grouping: {}", group_by.name()));
+    }
+
+    fn cycle_group_by(&mut self) {
+        self.set_group_by(self.group_by.next());
+    }
+
+    fn perform(&mut self, action: Action) {
+        if self.view == View::Detail {
+            // Navigation scrolls the detail view; quit and details return from it
+            match action {
+                Action::Quit | Action::Details => return self.close_details(),
+                Action::Next => {
+                    self.detail_scroll = self.detail_scroll.saturating_add(1);
+                    return;
+                }
+                Action::Previous => {
+                    self.detail_scroll = self.detail_scroll.saturating_sub(1);
+                    return;
+                }
+                Action::Dashboard
+                | Action::CycleGroup
+                | Action::ToggleGroup
+                | Action::Left
+                | Action::Right => return,
+                _ => {}
+            }
+        }
+        if self.view == View::Dashboard {
+            let movement = match action {
+                Action::Left => Some((-1, 0)),
+                Action::Right => Some((1, 0)),
+                Action::Previous => Some((0, -1)),
+                Action::Next => Some((0, 1)),
+                _ => None,
+            };
+            if let Some((dx, dy)) = movement {
+                return self.dashboard_move(dx, dy);
+            }
+        }
+        match action {
+            Action::Quit => self.should_quit = true,
+            Action::CommandMode => self.mode = AppMode::Command,
+            Action::Next => self.next(),
+            Action::Previous => self.previous(),
+            // Only the dashboard moves sideways
+            Action::Left | Action::Right => {}
+            Action::Select => self.select(),
+            Action::CycleTheme => self.cycle_theme(),
+            Action::CycleGroup => self.cycle_group_by(),
+            Action::ToggleGroup => self.toggle_selected_group(),
+            Action::Peek => {
+                if let Err(e) = self.request_peek("") {
+                    self.error(e);
+                }
+            }
+            Action::Pull => {
+                if let Err(e) = self.run_command("pull") {
+                    self.error(e);
+                }
+            }
+            Action::Push => {
+                if let Err(e) = self.run_command("push") {
+                    self.error(e);
+                }
+            }
+            Action::Dashboard => self.toggle_dashboard(),
+            Action::Details => self.open_details(),
+            Action::KillServer => {
+                if let Err(e) = self.run_command("kill-server") {
+                    self.error(e);
+                }
+            }
+            Action::CopyUrl => {
+                if let Err(e) = self.run_command("copy-url") {
+                    self.error(e);
+                }
+            }
+        }
+    }
+
+    fn set_theme(&mut self, name: &str) {
+        if let Some((name, theme)) = self.themes.get(name) {
+            let _ = save_theme(&name);
+            self.info(format!("Theme set to: {}", name));
+            self.theme_name = name;
+            self.theme = theme;
+        }
+    }
+
+    fn cycle_theme(&mut self) {
+        let next = self.themes.next(&self.theme_name);
+        self.set_theme(&next);
+    }
+
+    pub(crate) fn info(&mut self, msg: String) {
+        self.status_message = Some(msg);
+        self.status_is_error = false;
+    }
+
+    pub(crate) fn error(&mut self, msg: String) {
+        self.status_message = Some(msg);
+        self.status_is_error = true;
+    }
+
+    fn clear_status(&mut self) {
+        self.status_message = None;
+        self.status_is_error = false;
+    }
+
+    fn matches_filter(&self, session: &AiSession) -> bool {
+        let Some(filter) = &self.filter else {
+            return true;
+        };
+        let filter = filter.to_lowercase();
+        [
+            Some(&session.agent_type),
+            Some(&session.working_dir),
+            session.name.as_ref(),
+            session.mux_session.as_ref(),
+        ]
+        .iter()
+        .flatten()
+        .any(|field| field.to_lowercase().contains(&filter))
+    }
+
+    fn set_sort_by(&mut self, sort_by: SortBy) {
+        self.sort_by = sort_by;
+        let sessions = std::mem::take(&mut self.sessions);
+        self.set_sessions(sessions);
+        self.config.sort_by = sort_by.name().to_string();
+        let _ = self.config.save();
+        self.info(format!("Sorted by: {}", sort_by.name()));
+    }
+
+    /// Resolve a command target to a session index, or the selected session if empty.
+    fn resolve_target(&self, target: &str) -> std::result::Result<usize, String> {
+        if target.is_empty() {
+            return self
+                .focused_session_index()
+                .ok_or_else(|| "No session selected".to_string());
+        }
+        resolve_session(&self.sessions, target)
+    }
+
+    /// Resolve a port argument to the session owning it and the port, or the
+    /// focused session's only port if empty.
+    fn resolve_port(
+        &self,
+        arg: &str,
+        command: &str,
+    ) -> std::result::Result<(usize, ListeningPort), String> {
+        if !arg.is_empty() {
+            let port: u16 = arg
+                .trim_start_matches(':')
+                .parse()
+                .map_err(|_| format!("Invalid port: {}", arg))?;
+            return self
+                .sessions
+                .iter()
+                .enumerate()
+                .find_map(|(i, s)| {
+                    s.ports
+                        .iter()
+                        .find(|p| p.port == port)
+                        .map(|p| (i, p.clone()))
+                })
+                .ok_or_else(|| format!("No session is listening on :{}", port));
+        }
+        let idx = self.resolve_target("")?;
+        match self.sessions[idx].ports.as_slice() {
+            [] => Err(format!(
+                "Session {} has no listening ports",
+                self.sessions[idx].id
+            )),
+            [port] => Ok((idx, port.clone())),
+            ports => Err(format!(
+                "Multiple ports: {}. Use: {} <port>",
+                ports
+                    .iter()
+                    .map(|p| format!(":{}", p.port))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                command
+            )),
+        }
+    }
+
+    fn toggle_dashboard(&mut self) {
+        match self.view {
+            View::List => {
+                // Start on the session selected in the list
+                self.dashboard_pid = self.selected_pid();
+                self.view = View::Dashboard;
+                self.refresh_captures();
+            }
+            View::Dashboard => {
+                if let Some(i) = self.focused_session_index() {
+                    if let Some(row) = self.rows.iter().position(|r| *r == ListRow::Session(i)) {
+                        self.list_state.select(Some(row));
+                    }
+                }
+                self.view = View::List;
+            }
+            View::Detail => {}
+        }
+    }
+
+    /// Session under the cursor in the list, dashboard or detail view.
+    fn focused_session_index(&self) -> Option<usize> {
+        match self.view {
+            View::Dashboard => {
+                let visible = self.dashboard_sessions();
+                visible.get(self.dashboard_position(&visible)).copied()
+            }
+            View::Detail => self.detail_session_index(),
+            View::List => self.selected_session_index(),
+        }
+    }
+
+    pub(crate) fn detail_session_index(&self) -> Option<usize> {
+        let pid = self.detail_pid?;
+        self.sessions.iter().position(|s| s.pid == pid)
+    }
+
+    fn open_details(&mut self) {
+        let Some(i) = self.focused_session_index() else {
+            self.error("No session selected".to_string());
+            return;
+        };
+        self.detail_return = self.view;
+        self.detail_pid = Some(self.sessions[i].pid);
+        self.detail_scroll = 0;
+        self.details = None;
+        self.view = View::Detail;
+        self.refresh_details();
+    }
+
+    fn close_details(&mut self) {
+        self.view = self.detail_return;
+        self.detail_pid = None;
+        self.details = None;
+    }
+
+    /// Rebuild the detail view's session from the last scan, after opening it
+    /// and after each refresh.
+    fn refresh_details(&mut self) {
+        let Some(pid) = self.detail_pid else {
+            return;
+        };
+        if self.detail_session_index().is_none() {
+            self.details = None;
+            return;
+        }
+        match self.scanner.details(pid) {
+            Ok(details) => self.details = Some(details),
+            Err(e) => self.error(format!("Failed to read process details: {}", e)),
+        }
+    }
+
+    /// Sessions shown in the dashboard: every session passing the filter,
+    /// including those inside collapsed groups.
+    pub(crate) fn dashboard_sessions(&self) -> Vec<usize> {
+        (0..self.sessions.len())
+            .filter(|&i| self.matches_filter(&self.sessions[i]))
+            .collect()
+    }
+
+    /// Re-capture the visible panes, at most every `DASHBOARD_CAPTURE_MS`.
+    fn refresh_captures(&mut self) {
+        if self
+            .last_capture
+            .is_some_and(|t| t.elapsed() < Duration::from_millis(DASHBOARD_CAPTURE_MS))
+        {
+            return;
+        }
+        let sessions: Vec<&AiSession> = self
+            .dashboard_sessions()
+            .iter()
+            .map(|&i| &self.sessions[i])
+            .filter(|s| s.tmux_pane().is_some())
+            .collect();
+        let pids: Vec<u32> = sessions.iter().map(|s| s.pid).collect();
+        self.pane_captures.retain(|pid, _| pids.contains(pid));
+        let mut captures = Vec::new();
+        for session in sessions {
+            let pane = session.tmux_pane().unwrap_or_default();
+            let socket = session.mux_socket.as_deref();
+            if let Ok(content) = run_tmux_on(socket, &["capture-pane", "-p", "-t", pane]) {
+                let mut lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+                while lines.last().is_some_and(|l| l.trim().is_empty()) {
+                    lines.pop();
+                }
+                captures.push((session.pid, lines));
+            }
+        }
+        self.pane_captures.extend(captures);
+        self.last_capture = Some(Instant::now());
+    }
+
+    /// Position in `visible` (from `dashboard_sessions()`) of the highlighted
+    /// cell: the one showing `dashboard_pid`, or the first when that agent is
+    /// gone or filtered out.
+    pub(crate) fn dashboard_position(&self, visible: &[usize]) -> usize {
+        visible
+            .iter()
+            .position(|&i| Some(self.sessions[i].pid) == self.dashboard_pid)
+            .unwrap_or(0)
+    }
+
+    /// Move the dashboard selection by whole cells in the grid of width `dashboard_cols`.
+    fn dashboard_move(&mut self, dx: isize, dy: isize) {
+        let visible = self.dashboard_sessions();
+        if visible.is_empty() {
+            return;
+        }
+        let cols = self.dashboard_cols.max(1) as isize;
+        let current = self.dashboard_position(&visible) as isize;
+        let target = current + dx + dy * cols;
+        if (0..visible.len() as isize).contains(&target) {
+            self.dashboard_pid = Some(self.sessions[visible[target as usize]].pid);
+        }
+    }
+
+    fn request_peek(&mut self, target: &str) -> std::result::Result<(), String> {
+        let idx = self.resolve_target(target)?;
+        if self.sessions[idx].tmux_pane().is_none() {
+            return Err("Session is not in tmux".to_string());
+        }
+        self.peek_request = Some(idx);
+        Ok(())
+    }
+
+    fn execute_command(&mut self) {
+        let input = self.command_input.trim().to_string();
+        // Text sent to agents can hold secrets, so it stays out of the history file
+        let is_send = input
+            .split_whitespace()
+            .next()
+            .is_some_and(|cmd| cmd.eq_ignore_ascii_case("send"));
+        if !input.is_empty() && !is_send && self.history.last() != Some(&input) {
+            self.history.push(input.clone());
+            let _ = save_command_history(&self.history);
+        }
+
+        self.command_input.clear();
+        self.history_idx = None;
+        self.completion = None;
+        self.mode = AppMode::Normal;
+
+        if let Err(e) = self.run_command(&input) {
+            self.error(e);
+        }
+    }
+
+    /// Ask for y/n before running `action`.
+    fn ask(&mut self, action: Confirm) {
+        self.confirm = Some(action);
+        self.mode = AppMode::Confirm;
+    }
+
+    /// Run or drop the action awaiting confirmation.
+    pub(crate) fn answer(&mut self, yes: bool) {
+        self.mode = AppMode::Normal;
+        let Some(action) = self.confirm.take() else {
+            return;
+        };
+        if !yes {
+            return self.info("Cancelled".to_string());
+        }
+        match action {
+            Confirm::Kill { pid, id, .. } => match kill_pid(pid) {
+                Ok(()) => self.info(format!("Killed session {} (PID: {})", id, pid)),
+                Err(e) => self.error(e.to_string()),
+            },
+            Confirm::KillServer { agent_pid, port } => match kill_pid(port.pid) {
+                Ok(()) => {
+                    if let Some(session) = self.sessions.iter_mut().find(|s| s.pid == agent_pid) {
+                        session.ports.retain(|p| p.pid != port.pid);
+                    }
+                    self.info(format!("Killed {} (PID: {})", port.label(), port.pid));
+                }
+                Err(e) => self.error(e.to_string()),
+            },
+        }
+    }
+
+    fn run_command(&mut self, input: &str) -> std::result::Result<(), String> {
+        let (cmd, args) = match input.split_once(char::is_whitespace) {
+            Some((cmd, args)) => (cmd.to_lowercase(), args.trim()),
+            None => (input.to_lowercase(), ""),
+        };
+
+        match cmd.as_str() {
+            "" => {}
+            "theme" => {
+                if args.is_empty() {
+                    self.cycle_theme();
+                } else if self.themes.get(args).is_some() {
+                    self.set_theme(args);
+                } else {
+                    return Err(format!(
+                        "Unknown theme. Available: {}",
+                        self.themes.names().join(", ")
+                    ));
+                }
+            }
+            "themes" | "list" => {
+                self.info(format!("Themes: {}", self.themes.names().join(", ")));
+            }
+            "q" | "quit" => self.should_quit = true,
+            "jump" => {
+                if args.is_empty() {
+                    return Err("Usage: jump <id|name>".to_string());
+                }
+                self.selected_session = Some(self.resolve_target(args)?);
+                self.should_quit = true;
+            }
+            "kill" => {
+                let session = &self.sessions[self.resolve_target(args)?];
+                self.ask(Confirm::Kill {
+                    pid: session.pid,
+                    id: session.id.clone(),
+                    agent: session.agent_type.clone(),
+                });
+            }
+            "rename" => {
+                let idx = self.resolve_target("")?;
+                let name = (!args.is_empty()).then_some(args);
+                let live: Vec<u32> = self.sessions.iter().map(|s| s.pid).collect();
+                save_session_name(self.sessions[idx].pid, name, &live)
+                    .map_err(|e| format!("Failed to save name: {}", e))?;
+                self.sessions[idx].name = name.map(|n| n.to_string());
+                self.rebuild_rows();
+                let id = &self.sessions[idx].id;
+                match name {
+                    Some(name) => self.info(format!("Renamed {} to: {}", id, name)),
+                    None => self.info(format!("Cleared name of {}", id)),
+                }
+            }
+            "peek" => self.request_peek(args)?,
+            "pull" => {
+                let horizontal = !args.split_whitespace().any(|a| a == "-v");
+                let target = args
+                    .split_whitespace()
+                    .find(|a| !a.starts_with('-'))
+                    .unwrap_or("");
+                let idx = self.resolve_target(target)?;
+                let msg = pull_pane(&self.sessions[idx], horizontal)?;
+                self.info(msg);
+            }
+            "push" => {
+                let idx = self.resolve_target(args)?;
+                let msg = push_pane(PushTarget::Session(&self.sessions[idx]))?;
+                self.info(msg);
+            }
+            "send" => {
+                if args.is_empty() {
+                    return Err("Usage: send <text>".to_string());
+                }
+                let idx = self.resolve_target("")?;
+                send_to_pane(&self.sessions[idx], args)?;
+                self.info(format!("Sent to {}", self.sessions[idx].id));
+            }
+            "kill-server" => {
+                let (idx, port) = self.resolve_port(args, "kill-server")?;
+                if port.pid == self.sessions[idx].pid {
+                    return Err(format!(
+                        "Port :{} belongs to the agent itself; use kill to stop the session",
+                        port.port
+                    ));
+                }
+                self.ask(Confirm::KillServer {
+                    agent_pid: self.sessions[idx].pid,
+                    port,
+                });
+            }
+            "copy-url" => {
+                let (_, port) = self.resolve_port(args, "copy-url")?;
+                copy_to_clipboard(&port.url())?;
+                self.info(format!("Copied {}", port.url()));
+            }
+            "filter" => {
+                self.filter = (!args.is_empty()).then(|| args.to_string());
+                self.rebuild_rows();
+                match &self.filter {
+                    Some(f) => self.info(format!("Filter: {} ({} shown)", f, self.visible_count())),
+                    None => self.info("Filter cleared".to_string()),
+                }
+            }
+            "sort" => {
+                let sort_by = SortBy::parse(args).ok_or_else(|| {
+                    format!(
+                        "Unknown sort key. Available: {}",
+                        SortBy::all()
+                            .iter()
+                            .map(|k| k.name())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                })?;
+                self.set_sort_by(sort_by);
+            }
+            "group" => {
+                let group_by = if args.is_empty() {
+                    self.group_by.next()
+                } else {
+                    GroupBy::parse(args).ok_or_else(|| {
+                        format!(
+                            "Unknown grouping. Available: {}",
+                            GroupBy::all()
+                                .iter()
+                                .map(|g| g.name())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        )
+                    })?
+                };
+                self.set_group_by(group_by);
+            }
+            "new" => {
+                let mut parts = args.splitn(2, char::is_whitespace);
+                let agent = parts.next().filter(|a| !a.is_empty());
+                let Some(agent) = agent else {
+                    return Err("Usage: new <agent> [dir]".to_string());
+                };
+                let dir = match parts.next().map(|d| d.trim()).filter(|d| !d.is_empty()) {
+                    Some(dir) => expand_tilde(dir),
+                    None => self
+                        .selected_session_index()
+                        .map(|i| self.sessions[i].working_dir.clone())
+                        .or_else(|| env::current_dir().ok().map(|d| d.display().to_string()))
+                        .unwrap_or_else(|| ".".to_string()),
+                };
+                if !std::path::Path::new(&dir).is_dir() {
+                    return Err(format!("Not a directory: {}", dir));
+                }
+                let target = new_agent_window(agent, &dir)?;
+                self.info(format!("Started {} in {}", agent, target));
+            }
+            "set" => {
+                let (key, value) = args
+                    .split_once(char::is_whitespace)
+                    .map(|(k, v)| (k, v.trim()))
+                    .ok_or_else(|| "Usage: set <key> <value>".to_string())?;
+                self.set_option(key, value)?;
+            }
+            other => return Err(format!("Unknown command: {}", other)),
+        }
+
+        Ok(())
+    }
+
+    fn visible_count(&self) -> usize {
+        self.rows
+            .iter()
+            .filter(|r| matches!(r, ListRow::Session(_)))
+            .count()
+    }
+
+    /// Change a config option, validate it and apply it to the running app.
+    fn set_option(&mut self, key: &str, value: &str) -> std::result::Result<(), String> {
+        let config = set_config_value(&self.config, key, value)?;
+
+        let theme = self
+            .themes
+            .get(&config.theme)
+            .ok_or_else(|| format!("Unknown theme: {}", config.theme))?;
+        let group_by = GroupBy::parse(&config.group_by)
+            .ok_or_else(|| format!("Unknown grouping: {}", config.group_by))?;
+        let sort_by = SortBy::parse(&config.sort_by)
+            .ok_or_else(|| format!("Unknown sort key: {}", config.sort_by))?;
+        let layout = ListLayout::from_config(&config)?;
+        if config.refresh_ms < MIN_REFRESH_MS {
+            return Err(format!("refresh_ms must be at least {}", MIN_REFRESH_MS));
+        }
+        if config.color != "auto" && ColorSupport::parse(&config.color).is_none() {
+            return Err(format!("Unknown color mode: {}", config.color));
+        }
+        let bindings = KeyBindings::from_config(&config.keys)?;
+
+        (self.theme_name, self.theme) = theme;
+        self.group_by = group_by;
+        self.sort_by = sort_by;
+        self.layout = layout;
+        self.color_support = ColorSupport::resolve(&config.color);
+        self.bindings = bindings;
+        self.config = config;
+        let sessions = std::mem::take(&mut self.sessions);
+        self.set_sessions(sessions);
+
+        self.config
+            .save()
+            .map_err(|e| format!("Failed to save config: {}", e))?;
+        self.info(format!("Set {} = {}", key, value));
+        Ok(())
+    }
+
+    fn history_prev(&mut self) {
+        if self.history.is_empty() {
+            return;
+        }
+        let idx = match self.history_idx {
+            Some(i) => i.saturating_sub(1),
+            None => self.history.len() - 1,
+        };
+        self.history_idx = Some(idx);
+        self.command_input = self.history[idx].clone();
+        self.completion = None;
+    }
+
+    fn history_next(&mut self) {
+        let Some(i) = self.history_idx else {
+            return;
+        };
+        if i + 1 < self.history.len() {
+            self.history_idx = Some(i + 1);
+            self.command_input = self.history[i + 1].clone();
+        } else {
+            self.history_idx = None;
+            self.command_input.clear();
+        }
+        self.completion = None;
+    }
+
+    /// Candidates for the word being typed, given the words before it.
+    fn completion_candidates(&self, words: &[&str], partial: &str) -> Vec<String> {
+        let session_names = || {
+            let mut names: Vec<String> = self
+                .sessions
+                .iter()
+                .flat_map(|s| s.name.clone().into_iter().chain(s.mux_session.clone()))
+                .collect();
+            names.sort();
+            names.dedup();
+            names
+        };
+        let candidates: Vec<String> = match words {
+            [] => COMMANDS
+                .iter()
+                .map(|(name, _, _)| name.to_string())
+                .collect(),
+            ["theme"] => self.themes.names(),
+            ["group"] => GroupBy::all()
+                .iter()
+                .map(|g| g.name().to_string())
+                .collect(),
+            ["sort"] => SortBy::all().iter().map(|k| k.name().to_string()).collect(),
+            ["jump"] | ["kill"] => session_names(),
+            ["new"] => AGENT_COMMANDS.iter().map(|a| a.to_string()).collect(),
+            ["new", _] => return complete_path(partial),
+            ["set"] => config_keys(),
+            ["set", "theme"] => self.themes.names(),
+            ["set", "group_by"] => GroupBy::all()
+                .iter()
+                .map(|g| g.name().to_string())
+                .collect(),
+            ["set", "sort_by"] => SortBy::all().iter().map(|k| k.name().to_string()).collect(),
+            ["set", "layout"] => vec!["compact".into(), "normal".into(), "custom".into()],
+            ["set", "color"] => ["auto", "truecolor", "256", "16", "none"]
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+            ["set", "ascii_symbols"] => vec!["true".into(), "false".into()],
+            _ => Vec::new(),
+        };
+        let partial_lower = partial.to_lowercase();
+        candidates
+            .into_iter()
+            .filter(|c| c.to_lowercase().starts_with(&partial_lower))
+            .collect()
+    }
+
+    pub(crate) fn complete(&mut self) {
+        // Repeated Tab cycles through the candidates of the last completion
+        if let Some(completion) = &mut self.completion {
+            if self.command_input == completion.current() {
+                completion.index = (completion.index + 1) % completion.candidates.len();
+                self.command_input = completion.current();
+                return;
+            }
+        }
+
+        let input = self.command_input.clone();
+        let split = input.rfind(char::is_whitespace).map(|i| i + 1).unwrap_or(0);
+        let (base, partial) = input.split_at(split);
+        let words: Vec<String> = base
+            .split_whitespace()
+            .enumerate()
+            .map(|(i, w)| {
+                if i == 0 {
+                    w.to_lowercase()
+                } else {
+                    w.to_string()
+                }
+            })
+            .collect();
+        let words: Vec<&str> = words.iter().map(|w| w.as_str()).collect();
+
+        let candidates = self.completion_candidates(&words, partial);
+        match candidates.len() {
+            0 => self.completion = None,
+            1 => {
+                let suffix = if candidates[0].ends_with('/') {
+                    ""
+                } else {
+                    " "
+                };
+                self.command_input = format!("{}{}{}", base, candidates[0], suffix);
+                self.completion = None;
+            }
+            _ => {
+                let completion = Completion {
+                    base: base.to_string(),
+                    candidates,
+                    index: 0,
+                };
+                self.command_input = completion.current();
+                self.completion = Some(completion);
+            }
+        }
+    }
+}
+
+fn setup_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>> {
+    enable_raw_mode()?;
+    let mut stdout = stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(stdout);
+    let terminal = Terminal::new(backend)?;
+    Ok(terminal)
+}
+
+fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    terminal.show_cursor()?;
+    Ok(())
+}
+
+pub(crate) fn run_tui(
+    sessions: Vec<AiSession>,
+    bindings: KeyBindings,
+) -> Result<Option<AiSession>> {
+    let mut terminal = setup_terminal()?;
+    let mut app = App::new(sessions, bindings);
+
+    loop {
+        terminal.draw(|frame| ui(frame, &mut app))?;
+
+        // Check for events with configurable timeout
+        let refresh_ms = app.config.refresh_ms.max(MIN_REFRESH_MS);
+        if event::poll(Duration::from_millis(refresh_ms))? {
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    // Clear status message on any key
+                    app.clear_status();
+
+                    match &app.mode {
+                        AppMode::Command => match key.code {
+                            KeyCode::Enter => {
+                                app.execute_command();
+                            }
+                            KeyCode::Esc => {
+                                app.command_input.clear();
+                                app.history_idx = None;
+                                app.completion = None;
+                                app.mode = AppMode::Normal;
+                            }
+                            KeyCode::Tab => {
+                                app.complete();
+                            }
+                            KeyCode::Up => {
+                                app.history_prev();
+                            }
+                            KeyCode::Down => {
+                                app.history_next();
+                            }
+                            KeyCode::Backspace => {
+                                app.command_input.pop();
+                                app.completion = None;
+                            }
+                            KeyCode::Char(c) => {
+                                app.command_input.push(c);
+                                app.completion = None;
+                            }
+                            _ => {}
+                        },
+                        AppMode::Confirm => {
+                            app.answer(matches!(key.code, KeyCode::Char('y' | 'Y')));
+                        }
+                        AppMode::Normal => {
+                            if let Some(action) = app.bindings.action_for(&key) {
+                                app.perform(action);
+                            }
+                        }
+                    }
+                }
+                Event::Mouse(mouse) if app.mode == AppMode::Normal => match mouse.kind {
+                    MouseEventKind::Down(_) => {
+                        // Calculate which row was clicked
+                        // Header is 3 lines, so list starts at row 3
+                        let list_start_row = 3u16;
+                        if mouse.row >= list_start_row && app.view == View::List {
+                            if let Some(row) = app.row_at((mouse.row - list_start_row) as usize) {
+                                app.list_state.select(Some(row));
+                            }
+                        }
+                    }
+                    MouseEventKind::ScrollDown => {
+                        app.perform(Action::Next);
+                    }
+                    MouseEventKind::ScrollUp => {
+                        app.perform(Action::Previous);
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        } else {
+            // Timeout - refresh sessions, preserving selection and collapsed groups
+            if let Ok(new_sessions) = app.scanner.scan() {
+                app.set_sessions(new_sessions);
+            }
+            if app.view == View::Dashboard {
+                app.refresh_captures();
+            }
+            if app.view == View::Detail {
+                app.refresh_details();
+            }
+            app.run_reaper();
+            app.last_refresh = Instant::now();
+        }
+
+        if let Some(idx) = app.peek_request.take() {
+            if let Some(session) = app.sessions.get(idx) {
+                if in_tmux_pane() {
+                    if let Err(e) = open_peek_popup(session) {
+                        app.error(e);
+                    }
+                } else {
+                    let pane_id = session.tmux_pane().unwrap_or_default();
+                    let socket = session.mux_socket.as_deref();
+                    if let Err(e) = peek_pane(socket, pane_id, &peek_title(session)) {
+                        app.error(format!("Peek failed: {}", e));
+                    }
+                    terminal.clear()?;
+                }
+            }
+        }
+
+        if app.should_quit {
+            break;
+        }
+    }
+
+    restore_terminal(&mut terminal)?;
+
+    Ok(app
+        .selected_session
+        .and_then(|i| app.sessions.get(i).cloned()))
+}
+
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::*;
+    use rpai::MultiplexerKind;
+    use rpai::SessionState;
+
+    pub(crate) fn fixture_sessions() -> Vec<AiSession> {
+        let mut claude = AiSession::new(4242, "claude", "/work/src/api".to_string());
+        claude.multiplexer = Some(MultiplexerKind::Tmux);
+        claude.pane_id = Some("%3".to_string());
+        claude.mux_session = Some("work".to_string());
+        claude.mux_window = Some("1".to_string());
+        claude.pane_width = Some(120);
+        claude.pane_height = Some(40);
+        claude.uptime_seconds = 3 * 3600 + 5 * 60;
+        claude.memory_mb = 812;
+        claude.cpu_percent = 42.5;
+        claude.state = SessionState::Running;
+        claude.activity = Some("running: cargo test -p api (2m)".to_string());
+        claude.ports = vec![ListeningPort::new(3000, 4300, "vite")];
+
+        let mut codex = AiSession::new(
+            5150,
+            "codex",
+            "/work/projects/platform/monorepo/services/billing-service/src/invoices".to_string(),
+        );
+        codex.uptime_seconds = 45 * 60;
+        codex.memory_mb = 256;
+        codex.cpu_percent = 0.4;
+
+        let mut gemini = AiSession::new(6001, "gemini", "/tmp/scratch".to_string());
+        gemini.name = Some("reviewer".to_string());
+        gemini.multiplexer = Some(MultiplexerKind::Tmux);
+        gemini.pane_id = Some("%7".to_string());
+        gemini.mux_session = Some("review".to_string());
+        gemini.mux_window = Some("0".to_string());
+        gemini.pane_width = Some(80);
+        gemini.pane_height = Some(24);
+        gemini.uptime_seconds = 30;
+        gemini.memory_mb = 128;
+        gemini.cpu_percent = 1.2;
+
+        vec![claude, codex, gemini]
+    }
+
+    pub(crate) fn fixture_app(
+        sessions: Vec<AiSession>,
+        configure: impl FnOnce(&mut Config),
+    ) -> App {
+        let mut config = Config::default();
+        config.color = "truecolor".to_string();
+        configure(&mut config);
+        let bindings =
+            KeyBindings::from_config(&HashMap::new()).expect("default bindings are valid");
+        App::with_config(sessions, bindings, config, ThemeSet::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::{fixture_app, fixture_sessions};
+    use super::*;
+    use crossterm::event::{KeyEvent, KeyModifiers};
+
+    #[test]
+    fn dashboard_follows_the_agent_and_the_bindings() {
+        let mut config = HashMap::new();
+        config.insert("ctrl-f".to_string(), "right".to_string());
+        let bindings = KeyBindings::from_config(&config).expect("valid bindings");
+        let mut app = App::with_config(
+            fixture_sessions(),
+            bindings,
+            Config::default(),
+            ThemeSet::default(),
+        );
+        app.view = View::Dashboard;
+        app.dashboard_cols = 2;
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        app.perform(Action::Right);
+        assert_eq!(app.focused_session_index(), Some(1));
+        let ctrl_f = KeyEvent::new(KeyCode::Char('f'), KeyModifiers::CONTROL);
+        app.perform(app.bindings.action_for(&ctrl_f).expect("ctrl-f is bound"));
+        assert_eq!(app.focused_session_index(), Some(2));
+        app.perform(app.bindings.action_for(&key(KeyCode::Char('k'))).unwrap());
+        assert_eq!(app.focused_session_index(), Some(0));
+        app.perform(app.bindings.action_for(&key(KeyCode::Down)).unwrap());
+        let pid = app.sessions[app.focused_session_index().unwrap()].pid;
+        assert_eq!(pid, 6001);
+
+        // A refresh that reorders the sessions keeps the highlight on the same agent
+        let mut sessions = fixture_sessions();
+        sessions.reverse();
+        app.sort_by = SortBy::Cpu;
+        app.set_sessions(sessions);
+        assert_eq!(app.sessions[app.focused_session_index().unwrap()].pid, pid);
+    }
+
+    #[test]
+    fn kills_wait_for_confirmation() {
+        let mut app = fixture_app(fixture_sessions(), |_| {});
+        app.run_command("kill 2").unwrap();
+        assert_eq!(app.mode, AppMode::Confirm);
+        let prompt = app.confirm.as_ref().map(Confirm::prompt).unwrap();
+        assert!(prompt.contains("PID: 5150"), "{}", prompt);
+
+        app.answer(false);
+        assert_eq!(app.mode, AppMode::Normal);
+        assert_eq!(app.confirm, None);
+        assert_eq!(app.status_message.as_deref(), Some("Cancelled"));
+
+        app.run_command("kill-server 3000").unwrap();
+        assert_eq!(app.mode, AppMode::Confirm);
+        let prompt = app.confirm.as_ref().map(Confirm::prompt).unwrap();
+        assert_eq!(prompt, "Kill :3000 (vite) (PID: 4300)? y/N");
+        app.answer(false);
+        assert_eq!(app.sessions[0].ports.len(), 1);
+    }
+
+    /// Rows as group keys and PIDs, e.g. `["work", "4242", "review"]`.
+    fn row_labels(app: &App) -> Vec<String> {
+        app.rows
+            .iter()
+            .map(|row| match *row {
+                ListRow::Group(g) => app.groups[g].key.clone(),
+                ListRow::Session(i) => app.sessions[i].pid.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn collapsed_groups_stay_collapsed_across_refreshes() {
+        let mut app = fixture_app(fixture_sessions(), |c| c.group_by = "session".to_string());
+        assert_eq!(
+            row_labels(&app),
+            ["work", "4242", "(no multiplexer)", "5150", "review", "6001"]
+        );
+        app.toggle_group(0);
+        assert_eq!(
+            row_labels(&app),
+            ["work", "(no multiplexer)", "5150", "review", "6001"]
+        );
+
+        let mut sessions = fixture_sessions();
+        sessions.reverse();
+        app.set_sessions(sessions);
+        assert!(app.collapsed.contains("work"));
+        assert!(!row_labels(&app).contains(&"4242".to_string()));
+    }
+
+    #[test]
+    fn selection_skips_sessions_in_collapsed_groups() {
+        let mut app = fixture_app(fixture_sessions(), |c| c.group_by = "session".to_string());
+        app.toggle_group(1);
+        assert_eq!(
+            row_labels(&app),
+            ["work", "4242", "(no multiplexer)", "review", "6001"]
+        );
+        let selected = |app: &App| row_labels(app)[app.list_state.selected().unwrap()].clone();
+
+        app.list_state.select(Some(1));
+        app.next();
+        assert_eq!(selected(&app), "(no multiplexer)");
+        app.next();
+        assert_eq!(selected(&app), "review");
+        app.previous();
+        app.previous();
+        assert_eq!(selected(&app), "4242");
+    }
+}
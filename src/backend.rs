@@ -0,0 +1,589 @@
+//! Where the scanner gets its data from: the process table and the terminal
+//! multiplexers. [`SystemProcesses`], [`Tmux`], [`Zellij`] and [`Screen`] talk to the real
+//! system; `FakeProcesses` and `FakeMultiplexer` are in-memory stand-ins for tests, built
+//! with the `test-util` feature.
+
+use crate::error::{Error, Result};
+use crate::process::{
+    get_cwd_via_lsof, get_process_rows, listening_ports_for, read_cpu_time, read_pss_kb, ProcessRow,
+};
+use crate::session::{AiSession, MultiplexerKind};
+use crate::tmux::{
+    all_panes, command, current_tmux_pane, discover_sockets, focus_pane, is_current_server, run,
+    run_on, shell_quote,
+};
+use crate::{screen, zellij};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+
+/// A source of process information.
+pub trait ProcessSource: Send + Sync {
+    /// A snapshot of every process, keyed by PID.
+    fn processes(&self) -> Result<HashMap<u32, ProcessRow>>;
+
+    /// Current working directory of a process.
+    fn cwd(&self, pid: u32) -> Option<String>;
+
+    /// Proportional set size of a process in KB, where the platform reports it.
+    fn pss_kb(&self, pid: u32) -> Option<u64>;
+
+    /// CPU time a process has used so far, where the platform reports it.
+    /// Without it, CPU use is `ps`'s average since the process started.
+    fn cpu_time(&self, pid: u32) -> Option<Duration>;
+
+    /// TCP ports in LISTEN state owned by any of `pids`, as (pid, port) pairs.
+    /// A scan asks once, for the processes of every agent.
+    fn listening_ports(&self, pids: &[u32]) -> Vec<(u32, u16)>;
+}
+
+/// A multiplexer pane.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Pane {
+    /// Multiplexer the pane belongs to
+    pub multiplexer: MultiplexerKind,
+    /// Pane id, e.g. "%3" in tmux, "terminal_2" in zellij or `<sty>:<window>` in screen
+    pub pane_id: String,
+    /// Session containing the pane
+    pub session: String,
+    /// Window (tmux, screen) or tab (zellij) containing the pane, when known
+    pub window: Option<String>,
+    /// Width in cells, when known
+    pub width: Option<u32>,
+    /// Height in cells, when known
+    pub height: Option<u32>,
+    /// Socket of the server the pane belongs to, None for the server plain
+    /// commands reach
+    pub socket: Option<String>,
+}
+
+/// A terminal multiplexer whose panes agents run in.
+pub trait Multiplexer: Send + Sync {
+    /// Every pane, keyed by the PID of the process running in it. `processes`
+    /// is the process table of the scan, for multiplexers that find their
+    /// panes among the children of their server processes.
+    fn panes(&self, processes: &HashMap<u32, ProcessRow>) -> Result<HashMap<u32, Pane>>;
+
+    /// The pane this process runs in, if any.
+    fn current_pane(&self) -> Option<String>;
+
+    /// Make the session's pane the active pane of its window, switching the
+    /// current client to it when running inside the same server. With `zoom`,
+    /// the pane is zoomed unless its window already is.
+    fn focus(&self, session: &AiSession, zoom: bool) -> Result<()>;
+
+    /// Show the session's pane when [`Multiplexer::focus`] could not switch
+    /// the current client to it: attach the terminal when not running inside
+    /// the multiplexer, which may replace the current process.
+    fn attach(&self, session: &AiSession) -> Result<()>;
+}
+
+/// The default multiplexers: every tmux server (see [`Tmux::with_sockets`]),
+/// zellij and screen.
+pub(crate) fn default_multiplexers(tmux_sockets: &[String]) -> Vec<Arc<dyn Multiplexer>> {
+    vec![
+        Arc::new(Tmux::with_sockets(tmux_sockets.to_vec())),
+        Arc::new(Zellij),
+        Arc::new(Screen),
+    ]
+}
+
+/// The panes of all `multiplexers`, keyed by the PID of the process running
+/// in them, with `processes` the process table of the scan.
+pub(crate) fn all_multiplexer_panes(
+    multiplexers: &[Arc<dyn Multiplexer>],
+    processes: &HashMap<u32, ProcessRow>,
+) -> HashMap<u32, Pane> {
+    multiplexers
+        .iter()
+        .flat_map(|m| m.panes(processes).unwrap_or_default())
+        .collect()
+}
+
+/// Walk up the parent chain from `pid` to the process running in a pane.
+/// Returns the chain of PIDs from `pid` up to and including the pane's process.
+pub(crate) fn find_pane_for_pid(
+    pid: u32,
+    process_map: &HashMap<u32, ProcessRow>,
+    panes: &HashMap<u32, Pane>,
+) -> Option<(Vec<u32>, Pane)> {
+    let mut current_pid = pid;
+    let mut chain = Vec::new();
+    let max_steps = 25;
+
+    for _ in 0..max_steps {
+        chain.push(current_pid);
+        if let Some(pane) = panes.get(&current_pid) {
+            return Some((chain, pane.clone()));
+        }
+
+        if let Some(process_info) = process_map.get(&current_pid) {
+            current_pid = process_info.ppid;
+
+            if current_pid == 0 || current_pid == 1 {
+                break;
+            }
+        } else {
+            break;
+        }
+    }
+
+    None
+}
+
+/// The session, window and pane of a session in `kind`.
+fn pane_location(session: &AiSession, kind: MultiplexerKind) -> Result<(&str, &str, &str)> {
+    if session.multiplexer != Some(kind) {
+        return Err(Error::NotInMultiplexer);
+    }
+    match (&session.mux_session, &session.mux_window, &session.pane_id) {
+        (Some(mux_session), window, Some(pane_id)) => {
+            Ok((mux_session, window.as_deref().unwrap_or_default(), pane_id))
+        }
+        _ => Err(Error::NotInMultiplexer),
+    }
+}
+
+/// Processes of this machine, read with `ps`, `/proc`, `lsof` and sysinfo.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemProcesses;
+
+impl ProcessSource for SystemProcesses {
+    fn processes(&self) -> Result<HashMap<u32, ProcessRow>> {
+        get_process_rows()
+    }
+
+    fn cwd(&self, pid: u32) -> Option<String> {
+        let mut system = System::new();
+        let sysinfo_pid = sysinfo::Pid::from_u32(pid);
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[sysinfo_pid]),
+            true,
+            ProcessRefreshKind::new().with_cwd(UpdateKind::Always),
+        );
+        system
+            .process(sysinfo_pid)
+            .and_then(|p| p.cwd())
+            .map(|p| p.display().to_string())
+            .or_else(|| get_cwd_via_lsof(pid))
+    }
+
+    fn pss_kb(&self, pid: u32) -> Option<u64> {
+        read_pss_kb(pid)
+    }
+
+    fn cpu_time(&self, pid: u32) -> Option<Duration> {
+        read_cpu_time(pid)
+    }
+
+    fn listening_ports(&self, pids: &[u32]) -> Vec<(u32, u16)> {
+        listening_ports_for(pids)
+    }
+}
+
+/// Every tmux server of the user: those with a socket in
+/// `$TMUX_TMPDIR/tmux-<uid>` (default `/tmp`), the current one and any extra sockets.
+#[derive(Debug, Clone, Default)]
+pub struct Tmux {
+    sockets: Vec<String>,
+}
+
+impl Tmux {
+    /// Also look for servers at `sockets`: paths, or names as for `tmux -L`.
+    pub fn with_sockets(sockets: Vec<String>) -> Self {
+        Self { sockets }
+    }
+}
+
+impl Multiplexer for Tmux {
+    fn panes(&self, _processes: &HashMap<u32, ProcessRow>) -> Result<HashMap<u32, Pane>> {
+        all_panes(&discover_sockets(&self.sockets))
+    }
+
+    fn current_pane(&self) -> Option<String> {
+        current_tmux_pane()
+    }
+
+    fn focus(&self, session: &AiSession, zoom: bool) -> Result<()> {
+        let (session_name, window_index, pane_id) = pane_location(session, MultiplexerKind::Tmux)?;
+        let socket = session.mux_socket.as_deref();
+        let window_target = format!("{}:{}", session_name, window_index);
+        if std::env::var("TMUX").is_ok() && is_current_server(socket) {
+            // Use switch-client when inside tmux, then focus the exact pane
+            let pane_target = format!("{}.{}", window_target, pane_id);
+            run_on(socket, &["switch-client", "-t", &pane_target])?;
+        }
+        focus_pane(socket, &window_target, pane_id, zoom)
+    }
+
+    fn attach(&self, session: &AiSession) -> Result<()> {
+        let (session_name, window_index, pane_id) = pane_location(session, MultiplexerKind::Tmux)?;
+        let socket = session.mux_socket.as_deref();
+        let pane_target = format!("{}:{}.{}", session_name, window_index, pane_id);
+        if std::env::var("TMUX").is_ok() {
+            let Some(socket) = socket.filter(|s| !is_current_server(Some(s))) else {
+                return Ok(());
+            };
+            // A client can't switch to another server, so nest one in a popup
+            let nested = format!(
+                "TMUX= tmux -S {} attach-session -t {}",
+                shell_quote(socket),
+                shell_quote(&pane_target)
+            );
+            run(&["display-popup", "-E", "-w", "90%", "-h", "90%", &nested])?;
+            return Ok(());
+        }
+
+        // Use attach-session when outside tmux - must exec to take over terminal
+        use std::os::unix::process::CommandExt;
+        let err = command(socket)
+            .args(["attach-session", "-t", &pane_target])
+            .exec();
+        // exec only returns on error
+        Err(Error::Command {
+            command: format!("tmux attach-session -t {}", pane_target),
+            message: err.to_string(),
+        })
+    }
+}
+
+/// Every zellij session of the user. Panes are found through the `ZELLIJ_*`
+/// variables zellij puts in the environment of pane processes, so this only
+/// sees panes on systems with `/proc`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Zellij;
+
+impl Multiplexer for Zellij {
+    fn panes(&self, processes: &HashMap<u32, ProcessRow>) -> Result<HashMap<u32, Pane>> {
+        Ok(zellij::get_zellij_panes(processes))
+    }
+
+    fn current_pane(&self) -> Option<String> {
+        zellij::current_zellij_pane()
+    }
+
+    /// zellij has no command to query or set fullscreen by pane, so `zoom` is ignored.
+    fn focus(&self, session: &AiSession, _zoom: bool) -> Result<()> {
+        let (mux_session, _, pane_id) = pane_location(session, MultiplexerKind::Zellij)?;
+        zellij::focus_pane(mux_session, session.mux_window.as_deref(), pane_id)
+    }
+
+    fn attach(&self, session: &AiSession) -> Result<()> {
+        let (mux_session, _, _) = pane_location(session, MultiplexerKind::Zellij)?;
+        match zellij::current_session() {
+            Some(current) if current == mux_session => return Ok(()),
+            Some(current) => {
+                return Err(Error::Command {
+                    command: format!("zellij attach {}", mux_session),
+                    message: format!("already inside zellij session {}", current),
+                })
+            }
+            None => {}
+        }
+        let attach = format!("zellij attach {}", shell_quote(mux_session));
+        if std::env::var("TMUX").is_ok() {
+            run(&["display-popup", "-E", "-w", "90%", "-h", "90%", &attach])?;
+            return Ok(());
+        }
+
+        use std::os::unix::process::CommandExt;
+        let err = std::process::Command::new("zellij")
+            .args(["attach", mux_session])
+            .exec();
+        // exec only returns on error
+        Err(Error::Command {
+            command: attach,
+            message: err.to_string(),
+        })
+    }
+}
+
+/// Every GNU screen session of the user. Windows are found through the `STY`
+/// and `WINDOW` variables screen gives window processes, so this only sees
+/// windows on systems with `/proc`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Screen;
+
+impl Multiplexer for Screen {
+    fn panes(&self, processes: &HashMap<u32, ProcessRow>) -> Result<HashMap<u32, Pane>> {
+        Ok(screen::get_screen_windows(processes))
+    }
+
+    fn current_pane(&self) -> Option<String> {
+        screen::current_screen_window()
+    }
+
+    /// Selects the window when the session is attached. Screen windows fill
+    /// the terminal, so `zoom` is ignored.
+    fn focus(&self, session: &AiSession, _zoom: bool) -> Result<()> {
+        let (sty, window, _) = pane_location(session, MultiplexerKind::Screen)?;
+        if screen::is_attached(sty) {
+            screen::select_window(sty, window)?;
+        }
+        Ok(())
+    }
+
+    fn attach(&self, session: &AiSession) -> Result<()> {
+        let (sty, window, _) = pane_location(session, MultiplexerKind::Screen)?;
+        if screen::is_attached(sty) {
+            return Ok(());
+        }
+        if std::env::var("TMUX").is_ok() {
+            let attach = format!("screen -r {} -p {}", shell_quote(sty), shell_quote(window));
+            run(&["display-popup", "-E", "-w", "90%", "-h", "90%", &attach])?;
+            return Ok(());
+        }
+
+        use std::os::unix::process::CommandExt;
+        let err = std::process::Command::new("screen")
+            .args(["-r", sty, "-p", window])
+            .exec();
+        // exec only returns on error
+        Err(Error::Command {
+            command: format!("screen -r {} -p {}", sty, window),
+            message: err.to_string(),
+        })
+    }
+}
+
+/// An in-memory process table.
+///
+/// ```
+/// use rpai::FakeProcesses;
+///
+/// let processes = FakeProcesses::new()
+///     .process(100, 1, "zsh", "-zsh")
+///     .process(200, 100, "claude", "claude --resume")
+///     .with_cpu(200, 12.5)
+///     .with_cwd(200, "/src/api");
+/// ```
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug, Clone, Default)]
+pub struct FakeProcesses {
+    rows: HashMap<u32, ProcessRow>,
+    cwds: HashMap<u32, String>,
+    pss_kb: HashMap<u32, u64>,
+    cpu_times: HashMap<u32, Duration>,
+    ports: Vec<(u32, u16)>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl FakeProcesses {
+    /// An empty process table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a process with no CPU or memory use.
+    pub fn process(mut self, pid: u32, ppid: u32, comm: &str, command: &str) -> Self {
+        self.rows.insert(
+            pid,
+            ProcessRow {
+                pid,
+                ppid,
+                comm: comm.to_string(),
+                cpu_percent: 0.0,
+                rss_kb: 0,
+                tty: "?".to_string(),
+                started: String::new(),
+                elapsed_seconds: 0,
+                command: command.to_string(),
+            },
+        );
+        self
+    }
+
+    /// Set the CPU usage of a process added with [`FakeProcesses::process`].
+    pub fn with_cpu(mut self, pid: u32, cpu_percent: f64) -> Self {
+        if let Some(row) = self.rows.get_mut(&pid) {
+            row.cpu_percent = cpu_percent;
+        }
+        self
+    }
+
+    /// Set the resident memory of a process added with [`FakeProcesses::process`].
+    pub fn with_rss_kb(mut self, pid: u32, rss_kb: u64) -> Self {
+        if let Some(row) = self.rows.get_mut(&pid) {
+            row.rss_kb = rss_kb;
+        }
+        self
+    }
+
+    /// Set how long ago a process added with [`FakeProcesses::process`] started.
+    pub fn with_elapsed(mut self, pid: u32, seconds: i64) -> Self {
+        if let Some(row) = self.rows.get_mut(&pid) {
+            row.elapsed_seconds = seconds;
+        }
+        self
+    }
+
+    /// Set the working directory of a process.
+    pub fn with_cwd(mut self, pid: u32, dir: &str) -> Self {
+        self.cwds.insert(pid, dir.to_string());
+        self
+    }
+
+    /// Set the CPU time a process has used. Without it, the scanner keeps the
+    /// CPU use set with [`FakeProcesses::with_cpu`].
+    pub fn with_cpu_time(mut self, pid: u32, cpu_time: Duration) -> Self {
+        self.cpu_times.insert(pid, cpu_time);
+        self
+    }
+
+    /// Set the proportional set size of a process.
+    pub fn with_pss_kb(mut self, pid: u32, pss_kb: u64) -> Self {
+        self.pss_kb.insert(pid, pss_kb);
+        self
+    }
+
+    /// Make a process listen on a TCP port.
+    pub fn with_listening_port(mut self, pid: u32, port: u16) -> Self {
+        self.ports.push((pid, port));
+        self
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl ProcessSource for FakeProcesses {
+    fn processes(&self) -> Result<HashMap<u32, ProcessRow>> {
+        Ok(self.rows.clone())
+    }
+
+    fn cwd(&self, pid: u32) -> Option<String> {
+        self.cwds.get(&pid).cloned()
+    }
+
+    fn pss_kb(&self, pid: u32) -> Option<u64> {
+        self.pss_kb.get(&pid).copied()
+    }
+
+    fn cpu_time(&self, pid: u32) -> Option<Duration> {
+        self.cpu_times.get(&pid).copied()
+    }
+
+    fn listening_ports(&self, pids: &[u32]) -> Vec<(u32, u16)> {
+        self.ports
+            .iter()
+            .filter(|(pid, _)| pids.contains(pid))
+            .copied()
+            .collect()
+    }
+}
+
+/// An in-memory multiplexer that records focus and attach calls.
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug, Default)]
+pub struct FakeMultiplexer {
+    panes: HashMap<u32, Pane>,
+    current_pane: Option<String>,
+    calls: std::sync::Mutex<Vec<String>>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl FakeMultiplexer {
+    /// A multiplexer without panes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an 80x24 tmux pane whose process is `pid`.
+    pub fn pane(mut self, pid: u32, pane_id: &str, session_name: &str, window_index: u32) -> Self {
+        self.panes.insert(
+            pid,
+            Pane {
+                multiplexer: MultiplexerKind::Tmux,
+                pane_id: pane_id.to_string(),
+                session: session_name.to_string(),
+                window: Some(window_index.to_string()),
+                width: Some(80),
+                height: Some(24),
+                socket: None,
+            },
+        );
+        self
+    }
+
+    /// Add a zellij pane of unknown size and tab whose process is `pid`.
+    pub fn zellij_pane(mut self, pid: u32, pane_id: &str, session: &str) -> Self {
+        self.panes.insert(
+            pid,
+            Pane {
+                multiplexer: MultiplexerKind::Zellij,
+                pane_id: pane_id.to_string(),
+                session: session.to_string(),
+                window: None,
+                width: None,
+                height: None,
+                socket: None,
+            },
+        );
+        self
+    }
+
+    /// Set the pane this process pretends to run in.
+    pub fn with_current_pane(mut self, pane_id: &str) -> Self {
+        self.current_pane = Some(pane_id.to_string());
+        self
+    }
+
+    /// Add a screen window whose process is `pid`.
+    pub fn screen_window(mut self, pid: u32, sty: &str, window: u32) -> Self {
+        self.panes.insert(
+            pid,
+            Pane {
+                multiplexer: MultiplexerKind::Screen,
+                pane_id: format!("{}:{}", sty, window),
+                session: sty.to_string(),
+                window: Some(window.to_string()),
+                width: None,
+                height: None,
+                socket: None,
+            },
+        );
+        self
+    }
+
+    /// The focus and attach calls made so far, e.g. `"focus work:1.%3 zoom"`.
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().map(|c| c.clone()).unwrap_or_default()
+    }
+
+    fn record(&self, call: String) {
+        if let Ok(mut calls) = self.calls.lock() {
+            calls.push(call);
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Multiplexer for FakeMultiplexer {
+    fn panes(&self, _processes: &HashMap<u32, ProcessRow>) -> Result<HashMap<u32, Pane>> {
+        Ok(self.panes.clone())
+    }
+
+    fn current_pane(&self) -> Option<String> {
+        self.current_pane.clone()
+    }
+
+    fn focus(&self, session: &AiSession, zoom: bool) -> Result<()> {
+        let kind = session.multiplexer.ok_or(Error::NotInMultiplexer)?;
+        let (mux_session, window, pane_id) = pane_location(session, kind)?;
+        self.record(format!(
+            "focus {}:{}.{}{}",
+            mux_session,
+            window,
+            pane_id,
+            if zoom { " zoom" } else { "" }
+        ));
+        Ok(())
+    }
+
+    fn attach(&self, session: &AiSession) -> Result<()> {
+        let kind = session.multiplexer.ok_or(Error::NotInMultiplexer)?;
+        let (mux_session, window, pane_id) = pane_location(session, kind)?;
+        self.record(format!("attach {}:{}.{}", mux_session, window, pane_id));
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+//! Command mode: the command list, history and completion.
+
+use crate::Result;
+use rpai::support::{config_dir, ensure_config_dir};
+use rpai::Config;
+use std::env;
+use std::fs;
+
+pub(crate) fn expand_tilde(path: &str) -> String {
+    match (path.strip_prefix('~'), env::var("HOME")) {
+        (Some(rest), Ok(home)) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{}", home, rest)
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// Return a copy of `config` with `key` set to `value`. String options take
+/// the value verbatim, other options parse it as JSON.
+pub(crate) fn set_config_value(
+    config: &Config,
+    key: &str,
+    value: &str,
+) -> std::result::Result<Config, String> {
+    let mut json = serde_json::to_value(config).map_err(|e| e.to_string())?;
+    let obj = json
+        .as_object_mut()
+        .ok_or_else(|| "config is not an object".to_string())?;
+    let current = obj.get(key).ok_or_else(|| {
+        let mut keys: Vec<&String> = obj.keys().collect();
+        keys.sort();
+        format!(
+            "Unknown config key '{}'. Available: {}",
+            key,
+            keys.iter()
+                .map(|k| k.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    })?;
+    let new_value = if current.is_string() {
+        serde_json::Value::String(value.to_string())
+    } else {
+        serde_json::from_str(value).map_err(|e| format!("Invalid value for {}: {}", key, e))?
+    };
+    obj.insert(key.to_string(), new_value);
+    serde_json::from_value(json).map_err(|e| format!("Invalid value for {}: {}", key, e))
+}
+
+pub(crate) fn config_keys() -> Vec<String> {
+    let mut keys: Vec<String> = serde_json::to_value(Config::default())
+        .ok()
+        .and_then(|v| v.as_object().map(|o| o.keys().cloned().collect()))
+        .unwrap_or_default();
+    keys.sort();
+    keys
+}
+
+/// Command-mode commands with their argument synopsis and description.
+pub(crate) const COMMANDS: &[(&str, &str, &str)] = &[
+    ("jump", "<id|name>", "Jump to a session"),
+    (
+        "kill",
+        "[id|name]",
+        "Terminate a session (default: selected)",
+    ),
+    (
+        "rename",
+        "[name]",
+        "Name the selected session (empty clears)",
+    ),
+    (
+        "peek",
+        "[id|name]",
+        "Peek at a session's pane (default: selected)",
+    ),
+    (
+        "pull",
+        "[id|name] [-h|-v]",
+        "Move a session's pane into this window",
+    ),
+    ("push", "[id|name]", "Send a pulled pane back to its window"),
+    (
+        "send",
+        "<text>",
+        "Type text into the selected session and press Enter",
+    ),
+    (
+        "kill-server",
+        "[port]",
+        "Kill the process listening on a port (default: the selected session's)",
+    ),
+    (
+        "copy-url",
+        "[port]",
+        "Copy http://localhost:<port> to the clipboard",
+    ),
+    (
+        "filter",
+        "[text]",
+        "Show only matching sessions (empty clears)",
+    ),
+    (
+        "sort",
+        "<key>",
+        "Sort by agent, cpu, mem, uptime, state, name, dir or recent",
+    ),
+    ("group", "<mode>", "Group by none, session, repo or agent"),
+    (
+        "new",
+        "<agent> [dir]",
+        "Start an agent in a new tmux window",
+    ),
+    ("set", "<key> <value>", "Change a config option"),
+    ("theme", "[name]", "Switch theme"),
+    ("themes", "", "List available themes"),
+    ("quit", "", "Quit"),
+];
+
+pub(crate) const AGENT_COMMANDS: &[&str] =
+    &["claude", "codex", "opencode", "gemini", "cursor-agent"];
+
+const HISTORY_LIMIT: usize = 500;
+
+pub(crate) fn load_command_history() -> Vec<String> {
+    fs::read_to_string(config_dir().join("history"))
+        .map(|content| content.lines().map(|l| l.to_string()).collect())
+        .unwrap_or_default()
+}
+
+pub(crate) fn save_command_history(history: &[String]) -> Result<()> {
+    let dir = ensure_config_dir()?;
+    let start = history.len().saturating_sub(HISTORY_LIMIT);
+    let mut content = history[start..].join("\n");
+    content.push('\n');
+    fs::write(dir.join("history"), content)?;
+    Ok(())
+}
+
+/// Directories completing a partially typed path, keeping a leading `~`.
+pub(crate) fn complete_path(partial: &str) -> Vec<String> {
+    let expanded = expand_tilde(partial);
+    let (dir, file_prefix) = match expanded.rfind('/') {
+        Some(i) => (&expanded[..=i], &expanded[i + 1..]),
+        None => ("./", expanded.as_str()),
+    };
+    let shown_dir = match partial.rfind('/') {
+        Some(i) => &partial[..=i],
+        None => "",
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut matches: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|name| name.starts_with(file_prefix))
+        .filter(|name| !name.starts_with('.') || file_prefix.starts_with('.'))
+        .map(|name| format!("{}{}/", shown_dir, name))
+        .collect();
+    matches.sort();
+    matches
+}
+
+/// State of an in-progress Tab completion, cycled by pressing Tab again.
+#[derive(Debug, Clone)]
+pub(crate) struct Completion {
+    pub(crate) base: String,
+    pub(crate) candidates: Vec<String>,
+    pub(crate) index: usize,
+}
+
+impl Completion {
+    pub(crate) fn current(&self) -> String {
+        format!("{}{}", self.base, self.candidates[self.index])
+    }
+}
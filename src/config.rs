@@ -0,0 +1,226 @@
+//! User configuration and the config directory.
+
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Directory holding rpai's config and state files, `~/.config/rpai`.
+pub fn config_dir() -> PathBuf {
+    env::var("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join(".config")
+        .join("rpai")
+}
+
+/// [`config_dir`], created if missing.
+pub fn ensure_config_dir() -> Result<PathBuf> {
+    let dir = config_dir();
+    if !dir.exists() {
+        fs::create_dir_all(&dir)?;
+    }
+    Ok(dir)
+}
+
+/// User configuration, stored in `~/.config/rpai/config.json`.
+///
+/// Every field has a default, so partial config files are valid. Build one with
+/// [`Config::load`] or [`Config::default`] and adjust the fields.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Config {
+    /// Theme name (default: gruvbox)
+    #[serde(default = "default_theme")]
+    pub theme: String,
+    /// CPU percentage threshold below which a process is considered idle (default: 3.0)
+    #[serde(default = "default_idle_threshold")]
+    pub idle_threshold: f64,
+    /// Refresh interval in milliseconds (default: 50)
+    #[serde(default = "default_refresh_ms")]
+    pub refresh_ms: u64,
+    /// Use ASCII symbols instead of unicode (default: false)
+    #[serde(default = "default_ascii_symbols")]
+    pub ascii_symbols: bool,
+    /// Group sessions by tmux session, repo or agent (default: none)
+    #[serde(default = "default_group_by")]
+    pub group_by: String,
+    /// Sort sessions by agent, cpu, mem, uptime, state, name, dir or recent (default: agent)
+    #[serde(default = "default_sort_by")]
+    pub sort_by: String,
+    /// Zoom the agent's pane after jumping to it (default: false)
+    #[serde(default = "default_zoom_on_jump")]
+    pub zoom_on_jump: bool,
+    /// Session list layout: compact, normal or custom (default: normal)
+    #[serde(default = "default_layout")]
+    pub layout: String,
+    /// Lines of columns for the custom layout, e.g. [["state", "agent", "cpu"], ["cwd"]]
+    #[serde(default)]
+    pub custom_layout: Vec<Vec<String>>,
+    /// Color capability: auto, truecolor, 256, 16 or none (default: auto)
+    #[serde(default = "default_color")]
+    pub color: String,
+    /// Extra key bindings, mapping key chords (e.g. "ctrl-n") to action names
+    #[serde(default)]
+    pub keys: HashMap<String, String>,
+    /// Warn when a session's process tree uses at least this many MB (default:
+    /// 0, off). Only the TUI checks it, on each refresh.
+    #[serde(default)]
+    pub memory_warning_mb: u64,
+    /// tmux servers to scan besides those in `/tmp/tmux-<uid>/`: socket paths
+    /// (`-S`) or names (`-L`)
+    #[serde(default)]
+    pub tmux_sockets: Vec<String>,
+    /// Idle session reaper policy (default: disabled)
+    #[serde(default)]
+    pub reaper: ReaperConfig,
+}
+
+/// Opt-in policy for stopping agents left waiting for a long time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ReaperConfig {
+    /// Run the reaper from the TUI loop (default: false)
+    #[serde(default)]
+    pub enabled: bool,
+    /// Hours in Waiting with no pane activity before a session is warned about (default: 24)
+    #[serde(default = "default_reap_idle_hours")]
+    pub idle_hours: f64,
+    /// What to do after the warning: warn, exit or signal (default: warn)
+    #[serde(default = "default_reap_action")]
+    pub action: String,
+    /// Signal sent by the signal action, or by exit when an agent has no exit command or
+    /// is not in tmux (default: TERM)
+    #[serde(default = "default_reap_signal")]
+    pub signal: String,
+    /// Minutes between the warning and the action (default: 30)
+    #[serde(default = "default_reap_grace_minutes")]
+    pub grace_minutes: f64,
+    /// Only log what would be done (default: false)
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Session names never reaped
+    #[serde(default)]
+    pub exempt_names: Vec<String>,
+    /// Tags (`#word` in a session's name) never reaped
+    #[serde(default)]
+    pub exempt_tags: Vec<String>,
+    /// Directories whose sessions (including subdirectories) are never reaped
+    #[serde(default)]
+    pub exempt_dirs: Vec<String>,
+}
+
+fn default_reap_idle_hours() -> f64 {
+    24.0
+}
+
+fn default_reap_action() -> String {
+    "warn".to_string()
+}
+
+fn default_reap_signal() -> String {
+    "TERM".to_string()
+}
+
+fn default_reap_grace_minutes() -> f64 {
+    30.0
+}
+
+impl Default for ReaperConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_hours: default_reap_idle_hours(),
+            action: default_reap_action(),
+            signal: default_reap_signal(),
+            grace_minutes: default_reap_grace_minutes(),
+            dry_run: false,
+            exempt_names: Vec::new(),
+            exempt_tags: Vec::new(),
+            exempt_dirs: Vec::new(),
+        }
+    }
+}
+
+fn default_theme() -> String {
+    "gruvbox".to_string()
+}
+
+fn default_idle_threshold() -> f64 {
+    3.0
+}
+
+fn default_refresh_ms() -> u64 {
+    50
+}
+
+fn default_ascii_symbols() -> bool {
+    false
+}
+
+fn default_sort_by() -> String {
+    "agent".to_string()
+}
+
+fn default_zoom_on_jump() -> bool {
+    false
+}
+
+fn default_layout() -> String {
+    "normal".to_string()
+}
+
+fn default_color() -> String {
+    "auto".to_string()
+}
+
+fn default_group_by() -> String {
+    "none".to_string()
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            theme: default_theme(),
+            idle_threshold: default_idle_threshold(),
+            refresh_ms: default_refresh_ms(),
+            ascii_symbols: default_ascii_symbols(),
+            group_by: default_group_by(),
+            sort_by: default_sort_by(),
+            zoom_on_jump: default_zoom_on_jump(),
+            layout: default_layout(),
+            custom_layout: Vec::new(),
+            color: default_color(),
+            keys: HashMap::new(),
+            memory_warning_mb: 0,
+            tmux_sockets: Vec::new(),
+            reaper: ReaperConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Load `config.json`, falling back to the defaults when it is missing or invalid.
+    pub fn load() -> Config {
+        let path = config_dir().join("config.json");
+        if path.exists() {
+            if let Ok(content) = fs::read_to_string(&path) {
+                if let Ok(config) = serde_json::from_str::<Config>(&content) {
+                    return config;
+                }
+            }
+        }
+        Config::default()
+    }
+
+    /// Write the config to `config.json`.
+    pub fn save(&self) -> Result<()> {
+        let dir = ensure_config_dir()?;
+        let path = dir.join("config.json");
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
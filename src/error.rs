@@ -0,0 +1,82 @@
+use std::fmt;
+
+/// Errors returned by the rpai library.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An external command (`ps`, `tmux`, `kill`, ...) could not be started or failed.
+    Command {
+        /// The command line that was run
+        command: String,
+        /// Its stderr, or why it could not be started
+        message: String,
+    },
+    /// The session is not running inside a multiplexer pane.
+    NotInMultiplexer,
+    /// Reading or writing a file under the config directory failed.
+    Io(std::io::Error),
+    /// A config or state file could not be serialized.
+    Json(serde_json::Error),
+}
+
+/// Result type of the rpai library.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Command { command, message } => write!(f, "{} failed: {}", command, message),
+            Error::NotInMultiplexer => write!(f, "session is not in a tmux, zellij or screen pane"),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Json(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+/// Run an external command, returning its trimmed stdout, or an
+/// [`Error::Command`] carrying its stderr when it fails.
+pub(crate) fn run_command(program: &str, args: &[&str]) -> Result<String> {
+    let command = || format!("{} {}", program, args.join(" "));
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| Error::Command {
+            command: command(),
+            message: e.to_string(),
+        })?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(Error::Command {
+            command: command(),
+            message: if stderr.is_empty() {
+                format!("exit status {}", output.status)
+            } else {
+                stderr
+            },
+        })
+    }
+}
@@ -0,0 +1,223 @@
+//! Grouping and sorting sessions for the list view.
+
+use rpai::support::load_mru;
+use rpai::{AiSession, SessionState};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GroupBy {
+    None,
+    Session,
+    Repo,
+    Agent,
+}
+
+impl GroupBy {
+    pub(crate) fn all() -> Vec<GroupBy> {
+        vec![
+            GroupBy::None,
+            GroupBy::Session,
+            GroupBy::Repo,
+            GroupBy::Agent,
+        ]
+    }
+
+    pub(crate) fn next(&self) -> GroupBy {
+        let all = Self::all();
+        let idx = all.iter().position(|g| g == self).unwrap_or(0);
+        all[(idx + 1) % all.len()]
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            GroupBy::None => "none",
+            GroupBy::Session => "session",
+            GroupBy::Repo => "repo",
+            GroupBy::Agent => "agent",
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<GroupBy> {
+        match s.to_lowercase().as_str() {
+            "none" | "off" | "flat" => Some(GroupBy::None),
+            "session" | "tmux" => Some(GroupBy::Session),
+            "repo" | "git" => Some(GroupBy::Repo),
+            "agent" | "type" => Some(GroupBy::Agent),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SessionGroup {
+    pub(crate) key: String,
+    pub(crate) sessions: Vec<usize>,
+}
+
+/// A single selectable row in the session list: either a group header or a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ListRow {
+    Group(usize),
+    Session(usize),
+}
+
+/// Walk up from `path` looking for a `.git` entry (directory or worktree file).
+/// None for relative paths such as "unknown", which would otherwise be looked
+/// up from rpai's own working directory.
+pub(crate) fn find_git_root(path: &str) -> Option<String> {
+    let path = std::path::Path::new(path);
+    if !path.is_absolute() {
+        return None;
+    }
+    let mut current = Some(path);
+    while let Some(dir) = current {
+        if dir.join(".git").exists() {
+            return Some(dir.display().to_string());
+        }
+        current = dir.parent();
+    }
+    None
+}
+
+/// Git roots already looked up, by working directory, so regrouping on every
+/// refresh doesn't walk the filesystem for each session again.
+pub(crate) type GitRoots = HashMap<String, Option<String>>;
+
+fn group_key(session: &AiSession, group_by: GroupBy, git_roots: &mut GitRoots) -> String {
+    match group_by {
+        GroupBy::None => String::new(),
+        GroupBy::Session => session
+            .mux_session
+            .clone()
+            .unwrap_or_else(|| "(no multiplexer)".to_string()),
+        GroupBy::Repo => git_roots
+            .entry(session.working_dir.clone())
+            .or_insert_with(|| find_git_root(&session.working_dir))
+            .clone()
+            .unwrap_or_else(|| "(no git repo)".to_string()),
+        GroupBy::Agent => session.agent_type.clone(),
+    }
+}
+
+/// Group the given sessions by key, keeping groups in order of first appearance.
+/// Git roots of directories no session is in any more are forgotten.
+pub(crate) fn group_sessions(
+    sessions: &[AiSession],
+    indices: &[usize],
+    group_by: GroupBy,
+    git_roots: &mut GitRoots,
+) -> Vec<SessionGroup> {
+    git_roots.retain(|dir, _| sessions.iter().any(|s| s.working_dir == *dir));
+    let mut groups: Vec<SessionGroup> = Vec::new();
+    for &i in indices {
+        let key = group_key(&sessions[i], group_by, git_roots);
+        if let Some(group) = groups.iter_mut().find(|g| g.key == key) {
+            group.sessions.push(i);
+        } else {
+            groups.push(SessionGroup {
+                key,
+                sessions: vec![i],
+            });
+        }
+    }
+    groups
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SortBy {
+    Agent,
+    Cpu,
+    Mem,
+    Uptime,
+    State,
+    Name,
+    Dir,
+    Recent,
+}
+
+impl SortBy {
+    pub(crate) fn all() -> Vec<SortBy> {
+        vec![
+            SortBy::Agent,
+            SortBy::Cpu,
+            SortBy::Mem,
+            SortBy::Uptime,
+            SortBy::State,
+            SortBy::Name,
+            SortBy::Dir,
+            SortBy::Recent,
+        ]
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            SortBy::Agent => "agent",
+            SortBy::Cpu => "cpu",
+            SortBy::Mem => "mem",
+            SortBy::Uptime => "uptime",
+            SortBy::State => "state",
+            SortBy::Name => "name",
+            SortBy::Dir => "dir",
+            SortBy::Recent => "recent",
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<SortBy> {
+        match s.to_lowercase().as_str() {
+            "memory" => Some(SortBy::Mem),
+            "cwd" => Some(SortBy::Dir),
+            "mru" => Some(SortBy::Recent),
+            other => Self::all().into_iter().find(|k| k.name() == other),
+        }
+    }
+}
+
+/// Sort sessions in place. Resource columns sort descending; ties fall back to PID.
+pub(crate) fn sort_sessions(sessions: &mut [AiSession], sort_by: SortBy) {
+    let mru = if sort_by == SortBy::Recent {
+        load_mru()
+    } else {
+        Vec::new()
+    };
+    let recency = |s: &AiSession| mru.iter().position(|m| m.holds(s)).unwrap_or(usize::MAX);
+    sessions.sort_by(|a, b| {
+        let primary = match sort_by {
+            SortBy::Agent => a.agent_type.cmp(&b.agent_type),
+            SortBy::Cpu => b.cpu_percent.total_cmp(&a.cpu_percent),
+            SortBy::Mem => b.memory_mb.cmp(&a.memory_mb),
+            SortBy::Uptime => b.uptime_seconds.cmp(&a.uptime_seconds),
+            SortBy::State => {
+                (a.state == SessionState::Waiting).cmp(&(b.state == SessionState::Waiting))
+            }
+            SortBy::Name => a
+                .name
+                .as_ref()
+                .or(a.mux_session.as_ref())
+                .cmp(&b.name.as_ref().or(b.mux_session.as_ref())),
+            SortBy::Dir => a.working_dir.cmp(&b.working_dir),
+            SortBy::Recent => recency(a).cmp(&recency(b)),
+        };
+        primary.then(a.pid.cmp(&b.pid))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_dirs_are_not_grouped_by_our_own_repo() {
+        // Tests run inside rpai's repository, so a relative lookup would find it
+        let session = AiSession::new(7, "claude", "unknown");
+        assert_eq!(find_git_root("unknown"), None);
+        let mut git_roots = GitRoots::new();
+        assert_eq!(
+            group_key(&session, GroupBy::Repo, &mut git_roots),
+            "(no git repo)"
+        );
+        assert_eq!(
+            group_key(&session, GroupBy::Session, &mut git_roots),
+            "(no multiplexer)"
+        );
+    }
+}
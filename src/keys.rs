@@ -0,0 +1,400 @@
+//! Key bindings: the actions the TUI understands and the keys bound to them.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Action {
+    Quit,
+    CommandMode,
+    Next,
+    Previous,
+    Left,
+    Right,
+    Select,
+    CycleTheme,
+    CycleGroup,
+    ToggleGroup,
+    Peek,
+    Pull,
+    Push,
+    Dashboard,
+    Details,
+    KillServer,
+    CopyUrl,
+}
+
+impl Action {
+    pub(crate) fn all() -> Vec<Action> {
+        vec![
+            Action::Next,
+            Action::Previous,
+            Action::Left,
+            Action::Right,
+            Action::Select,
+            Action::Peek,
+            Action::Pull,
+            Action::Push,
+            Action::Dashboard,
+            Action::Details,
+            Action::KillServer,
+            Action::CopyUrl,
+            Action::ToggleGroup,
+            Action::CycleGroup,
+            Action::CycleTheme,
+            Action::CommandMode,
+            Action::Quit,
+        ]
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::CommandMode => "command",
+            Action::Next => "next",
+            Action::Previous => "previous",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Select => "select",
+            Action::CycleTheme => "cycle-theme",
+            Action::CycleGroup => "cycle-group",
+            Action::ToggleGroup => "toggle-group",
+            Action::Peek => "peek",
+            Action::Pull => "pull",
+            Action::Push => "push",
+            Action::Dashboard => "dashboard",
+            Action::Details => "details",
+            Action::KillServer => "kill-server",
+            Action::CopyUrl => "copy-url",
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<Action> {
+        Self::all()
+            .into_iter()
+            .find(|a| a.name() == s.to_lowercase())
+    }
+
+    pub(crate) fn description(&self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::CommandMode => "Enter command mode",
+            Action::Next => "Next session",
+            Action::Previous => "Previous session",
+            Action::Left => "Move left in the dashboard",
+            Action::Right => "Move right in the dashboard",
+            Action::Select => "Jump to selected session / toggle group",
+            Action::CycleTheme => "Cycle through themes",
+            Action::CycleGroup => "Cycle grouping (none, session, repo, agent)",
+            Action::ToggleGroup => "Collapse/expand current group",
+            Action::Peek => "Peek at the selected session's pane",
+            Action::Pull => "Pull the selected session's pane into this window",
+            Action::Push => "Push the selected session's pane back to its window",
+            Action::Dashboard => "Toggle the dashboard grid of live panes",
+            Action::Details => "Show details and process tree for the selected session",
+            Action::KillServer => "Kill the server listening on the selected session's port",
+            Action::CopyUrl => "Copy the URL of the selected session's listening port",
+        }
+    }
+
+    /// Short label used in the TUI help bar.
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::CommandMode => "cmd",
+            Action::Next => "down",
+            Action::Previous => "up",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Select => "jump",
+            Action::CycleTheme => "theme",
+            Action::CycleGroup => "group",
+            Action::ToggleGroup => "fold",
+            Action::Peek => "peek",
+            Action::Pull => "pull",
+            Action::Push => "push",
+            Action::Dashboard => "grid",
+            Action::Details => "info",
+            Action::KillServer => "kill srv",
+            Action::CopyUrl => "url",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KeyChord {
+    pub(crate) code: KeyCode,
+    pub(crate) modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub(crate) fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let (code, modifiers) = match code {
+            // Terminals send the same control code for Ctrl with either case of a
+            // letter, which crossterm reports as the lowercase letter
+            KeyCode::Char(c)
+                if modifiers.contains(KeyModifiers::CONTROL) && c.is_ascii_alphabetic() =>
+            {
+                (
+                    KeyCode::Char(c.to_ascii_lowercase()),
+                    modifiers - KeyModifiers::SHIFT,
+                )
+            }
+            // Shifted characters arrive as uppercase chars, so SHIFT is redundant for them
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => (
+                KeyCode::Char(c.to_ascii_uppercase()),
+                modifiers - KeyModifiers::SHIFT,
+            ),
+            _ => (code, modifiers),
+        };
+        Self { code, modifiers }
+    }
+
+    fn from_event(key: &KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+
+    /// Parse chords such as "j", "ctrl-n", "alt-shift-x", "C-p", "Enter" or "-".
+    pub(crate) fn parse(s: &str) -> std::result::Result<KeyChord, String> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("empty key chord".to_string());
+        }
+
+        let (mods, key) = if s == "-" || !s.contains('-') {
+            ("", s)
+        } else if let Some(rest) = s.strip_suffix("--") {
+            (rest, "-")
+        } else {
+            s.rsplit_once('-').unwrap_or(("", s))
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for m in mods.split('-').filter(|m| !m.is_empty()) {
+            modifiers |= match m.to_lowercase().as_str() {
+                "ctrl" | "control" | "c" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "m" | "a" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier '{}' in '{}'", m, s)),
+            };
+        }
+
+        let code = match key.to_lowercase().as_str() {
+            "enter" | "return" | "ret" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "space" | "spc" => KeyCode::Char(' '),
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" | "bs" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            lower => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                        Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                        _ => return Err(format!("unknown key '{}' in '{}'", key, s)),
+                    },
+                }
+            }
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+
+    pub(crate) fn display(&self) -> String {
+        let mut out = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            out.push_str("Ctrl-");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            out.push_str("Alt-");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            out.push_str("Shift-");
+        }
+        match self.code {
+            KeyCode::Char(' ') => out.push_str("Space"),
+            KeyCode::Char(c) => out.push(c),
+            KeyCode::Enter => out.push_str("Enter"),
+            KeyCode::Esc => out.push_str("Esc"),
+            KeyCode::Tab => out.push_str("Tab"),
+            KeyCode::BackTab => out.push_str("BackTab"),
+            KeyCode::Backspace => out.push_str("Backspace"),
+            KeyCode::Delete => out.push_str("Delete"),
+            KeyCode::Up => out.push_str("Up"),
+            KeyCode::Down => out.push_str("Down"),
+            KeyCode::Left => out.push_str("Left"),
+            KeyCode::Right => out.push_str("Right"),
+            KeyCode::Home => out.push_str("Home"),
+            KeyCode::End => out.push_str("End"),
+            KeyCode::PageUp => out.push_str("PageUp"),
+            KeyCode::PageDown => out.push_str("PageDown"),
+            KeyCode::F(n) => out.push_str(&format!("F{}", n)),
+            other => out.push_str(&format!("{:?}", other)),
+        }
+        out
+    }
+}
+
+const DEFAULT_KEYS: &[(&str, Action)] = &[
+    ("j", Action::Next),
+    ("down", Action::Next),
+    ("k", Action::Previous),
+    ("up", Action::Previous),
+    ("h", Action::Left),
+    ("left", Action::Left),
+    ("l", Action::Right),
+    ("right", Action::Right),
+    ("enter", Action::Select),
+    ("space", Action::ToggleGroup),
+    ("p", Action::Peek),
+    ("u", Action::Pull),
+    ("shift-u", Action::Push),
+    ("d", Action::Dashboard),
+    ("tab", Action::Details),
+    ("i", Action::Details),
+    ("x", Action::KillServer),
+    ("y", Action::CopyUrl),
+    ("g", Action::CycleGroup),
+    ("t", Action::CycleTheme),
+    ("/", Action::CommandMode),
+    (":", Action::CommandMode),
+    ("q", Action::Quit),
+    ("esc", Action::Quit),
+    ("ctrl-c", Action::Quit),
+];
+
+/// Active key bindings: the defaults overlaid with the user's `keys` table.
+#[derive(Debug, Clone)]
+pub(crate) struct KeyBindings {
+    bindings: Vec<(KeyChord, Action)>,
+}
+
+impl KeyBindings {
+    /// Build bindings from the config `keys` table. Mapping a chord to "none"
+    /// removes its default binding. Unknown chords or actions are an error.
+    pub(crate) fn from_config(keys: &HashMap<String, String>) -> std::result::Result<Self, String> {
+        let mut bindings: Vec<(KeyChord, Action)> = DEFAULT_KEYS
+            .iter()
+            .map(|(chord, action)| (KeyChord::parse(chord).expect("valid default key"), *action))
+            .collect();
+
+        // Sort for deterministic error messages and ordering
+        let mut entries: Vec<(&String, &String)> = keys.iter().collect();
+        entries.sort();
+
+        for (chord_str, action_str) in entries {
+            let chord = KeyChord::parse(chord_str)
+                .map_err(|e| format!("Invalid key binding \"{}\": {}", chord_str, e))?;
+            bindings.retain(|(c, _)| *c != chord);
+            if action_str.eq_ignore_ascii_case("none") {
+                continue;
+            }
+            let action = Action::parse(action_str).ok_or_else(|| {
+                format!(
+                    "Invalid key binding \"{}\": unknown action '{}'. Available: {}",
+                    chord_str,
+                    action_str,
+                    Action::all()
+                        .iter()
+                        .map(|a| a.name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })?;
+            bindings.push((chord, action));
+        }
+
+        Ok(Self { bindings })
+    }
+
+    pub(crate) fn action_for(&self, key: &KeyEvent) -> Option<Action> {
+        let chord = KeyChord::from_event(key);
+        self.bindings
+            .iter()
+            .find(|(c, _)| *c == chord)
+            .map(|(_, a)| *a)
+    }
+
+    pub(crate) fn chords_for(&self, action: Action) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(c, _)| c.display())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_chords_parse() {
+        let chord = |code, modifiers| Ok(KeyChord::new(code, modifiers));
+        assert_eq!(
+            KeyChord::parse("j"),
+            chord(KeyCode::Char('j'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            KeyChord::parse("J"),
+            chord(KeyCode::Char('J'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            KeyChord::parse("shift-j"),
+            chord(KeyCode::Char('J'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            KeyChord::parse("-"),
+            chord(KeyCode::Char('-'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            KeyChord::parse("alt--"),
+            chord(KeyCode::Char('-'), KeyModifiers::ALT)
+        );
+        assert_eq!(
+            KeyChord::parse("C-p"),
+            chord(KeyCode::Char('p'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            KeyChord::parse(" Enter "),
+            chord(KeyCode::Enter, KeyModifiers::NONE)
+        );
+        assert_eq!(
+            KeyChord::parse("F5"),
+            chord(KeyCode::F(5), KeyModifiers::NONE)
+        );
+        assert!(KeyChord::parse("").is_err());
+        assert!(KeyChord::parse("hyper-x").is_err());
+        assert!(KeyChord::parse("f13").is_err());
+        assert!(KeyChord::parse("ctrl-xy").is_err());
+    }
+
+    #[test]
+    fn ctrl_letters_match_whatever_the_case() {
+        // crossterm reports Ctrl-C as a lowercase 'c'
+        let event = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        for config in ["ctrl-c", "ctrl-C", "ctrl-shift-c"] {
+            assert_eq!(
+                KeyChord::parse(config),
+                Ok(KeyChord::from_event(&event)),
+                "{}",
+                config
+            );
+        }
+        let mut keys = HashMap::new();
+        keys.insert("ctrl-N".to_string(), "next".to_string());
+        let bindings = KeyBindings::from_config(&keys).expect("valid bindings");
+        let event = KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL);
+        assert_eq!(bindings.action_for(&event), Some(Action::Next));
+    }
+}
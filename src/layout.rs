@@ -0,0 +1,365 @@
+//! List columns and the per-session data they show.
+
+use crate::grouping::find_git_root;
+use rpai::{AiSession, Config};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Column {
+    State,
+    Agent,
+    Name,
+    Pid,
+    Uptime,
+    Cpu,
+    Mem,
+    Tmux,
+    Cwd,
+    Git,
+    Tokens,
+    Activity,
+    Ports,
+}
+
+impl Column {
+    pub(crate) fn all() -> Vec<Column> {
+        vec![
+            Column::State,
+            Column::Agent,
+            Column::Name,
+            Column::Pid,
+            Column::Uptime,
+            Column::Cpu,
+            Column::Mem,
+            Column::Tmux,
+            Column::Cwd,
+            Column::Git,
+            Column::Tokens,
+            Column::Activity,
+            Column::Ports,
+        ]
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Column::State => "state",
+            Column::Agent => "agent",
+            Column::Name => "name",
+            Column::Pid => "pid",
+            Column::Uptime => "uptime",
+            Column::Cpu => "cpu",
+            Column::Mem => "mem",
+            Column::Tmux => "tmux",
+            Column::Cwd => "cwd",
+            Column::Git => "git",
+            Column::Tokens => "tokens",
+            Column::Activity => "activity",
+            Column::Ports => "ports",
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<Column> {
+        Self::all()
+            .into_iter()
+            .find(|c| c.name() == s.to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ListLayout {
+    /// One line per session
+    Compact,
+    /// The classic four-line item
+    Normal,
+    /// User-defined lines of columns
+    Custom(Vec<Vec<Column>>),
+}
+
+impl ListLayout {
+    pub(crate) fn from_config(config: &Config) -> std::result::Result<ListLayout, String> {
+        match config.layout.to_lowercase().as_str() {
+            "compact" => Ok(ListLayout::Compact),
+            "normal" => Ok(ListLayout::Normal),
+            "custom" => {
+                if config.custom_layout.is_empty() {
+                    return Err("layout is \"custom\" but custom_layout is empty".to_string());
+                }
+                let lines = config
+                    .custom_layout
+                    .iter()
+                    .map(|line| {
+                        line.iter()
+                            .map(|col| {
+                                Column::parse(col).ok_or_else(|| {
+                                    format!(
+                                        "unknown layout column '{}'. Available: {}",
+                                        col,
+                                        Column::all()
+                                            .iter()
+                                            .map(|c| c.name())
+                                            .collect::<Vec<_>>()
+                                            .join(", ")
+                                    )
+                                })
+                            })
+                            .collect::<std::result::Result<Vec<_>, _>>()
+                    })
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                Ok(ListLayout::Custom(lines))
+            }
+            other => Err(format!(
+                "unknown layout '{}'. Available: compact, normal, custom",
+                other
+            )),
+        }
+    }
+
+    /// Whether the layout shows `column`.
+    fn shows(&self, column: Column) -> bool {
+        match self {
+            ListLayout::Custom(lines) => lines.iter().any(|line| line.contains(&column)),
+            _ => false,
+        }
+    }
+
+    /// Number of terminal lines each session occupies in the list.
+    pub(crate) fn lines_per_item(&self) -> usize {
+        match self {
+            ListLayout::Compact => 1,
+            ListLayout::Normal => 4,
+            ListLayout::Custom(lines) => lines.len(),
+        }
+    }
+}
+
+/// Current branch (or short commit when detached) of the repository containing `path`.
+fn git_branch(path: &str) -> Option<String> {
+    let root = PathBuf::from(find_git_root(path)?);
+    let dot_git = root.join(".git");
+    // Worktrees and submodules use a `.git` file pointing at the real git dir
+    let git_dir = if dot_git.is_file() {
+        let content = fs::read_to_string(&dot_git).ok()?;
+        let target = content.trim().strip_prefix("gitdir:")?.trim().to_string();
+        root.join(target)
+    } else {
+        dot_git
+    };
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    match head.strip_prefix("ref: ") {
+        Some(reference) => Some(
+            reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference)
+                .to_string(),
+        ),
+        None => Some(head.chars().take(7).collect()),
+    }
+}
+
+/// The conversation log an agent is writing, where rpai knows where to look:
+/// for claude, the newest `.jsonl` in its project directory (named after the
+/// working directory, with every other character than letters and digits
+/// replaced by '-'); for codex, the newest rollout recorded for the directory.
+fn transcript_path(session: &AiSession) -> Option<PathBuf> {
+    let home = PathBuf::from(env::var("HOME").ok()?);
+    let newest = |paths: Vec<PathBuf>| {
+        paths
+            .into_iter()
+            .filter(|p| p.extension().is_some_and(|ext| ext == "jsonl"))
+            .filter_map(|p| Some((fs::metadata(&p).ok()?.modified().ok()?, p)))
+            .max()
+            .map(|(_, p)| p)
+    };
+    let list = |dir: &std::path::Path| -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .map(|entries| entries.flatten().map(|e| e.path()).collect())
+            .unwrap_or_default()
+    };
+    match session.agent_type.as_str() {
+        "claude" => {
+            let root = env::var("CLAUDE_CONFIG_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| home.join(".claude"));
+            let project: String = session
+                .working_dir
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+                .collect();
+            newest(list(&root.join("projects").join(project)))
+        }
+        "codex" => {
+            let root = env::var("CODEX_HOME")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| home.join(".codex"));
+            // Rollouts live in sessions/YYYY/MM/DD; the agent's is at most as
+            // old as the agent, so only the newest days need looking at
+            let mut days: Vec<PathBuf> = list(&root.join("sessions"))
+                .iter()
+                .flat_map(|year| list(year))
+                .flat_map(|month| list(&month))
+                .collect();
+            days.sort();
+            let needed = (session.uptime_seconds / 86400 + 2) as usize;
+            let rollouts: Vec<PathBuf> = days
+                .iter()
+                .rev()
+                .take(needed)
+                .flat_map(|day| list(day))
+                .filter(|p| codex_rollout_cwd(p).as_deref() == Some(session.working_dir.as_str()))
+                .collect();
+            newest(rollouts)
+        }
+        _ => None,
+    }
+}
+
+/// Working directory recorded in the `session_meta` line opening a codex rollout.
+fn codex_rollout_cwd(path: &std::path::Path) -> Option<String> {
+    use std::io::BufRead;
+    let file = fs::File::open(path).ok()?;
+    let mut first = String::new();
+    std::io::BufReader::new(file).read_line(&mut first).ok()?;
+    let meta: serde_json::Value = serde_json::from_str(&first).ok()?;
+    meta["payload"]["cwd"].as_str().map(str::to_string)
+}
+
+/// How much of a transcript's end is searched for the latest token usage.
+const TRANSCRIPT_TAIL_BYTES: u64 = 1024 * 1024;
+
+/// Tokens in the context at the agent's latest turn, from the last usage
+/// record in the transcript: a claude message's `usage` or a codex
+/// `token_count` event.
+fn transcript_tokens(path: &std::path::Path) -> Option<u64> {
+    use std::io::{Read, Seek, SeekFrom};
+    let mut file = fs::File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let start = len.saturating_sub(TRANSCRIPT_TAIL_BYTES);
+    file.seek(SeekFrom::Start(start)).ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+    let tail = String::from_utf8_lossy(&tail);
+    // Unless it is the whole file, the tail starts mid-record
+    let records = match start {
+        0 => &tail[..],
+        _ => tail.split_once('\n').map_or("", |(_, rest)| rest),
+    };
+    records.lines().rev().find_map(line_tokens)
+}
+
+/// Token count of one transcript line, if it records usage.
+fn line_tokens(line: &str) -> Option<u64> {
+    if !line.contains("\"usage\"") && !line.contains("\"token_count\"") {
+        return None;
+    }
+    let record: serde_json::Value = serde_json::from_str(line).ok()?;
+    let usage = &record["message"]["usage"];
+    if usage.is_object() {
+        let field = |name: &str| usage[name].as_u64().unwrap_or(0);
+        return Some(
+            field("input_tokens")
+                + field("cache_creation_input_tokens")
+                + field("cache_read_input_tokens")
+                + field("output_tokens"),
+        );
+    }
+    record["payload"]["info"]["last_token_usage"]["total_tokens"].as_u64()
+}
+
+/// Token count as "850", "12.3k" or "1.2M".
+pub(crate) fn format_tokens(tokens: u64) -> String {
+    match tokens {
+        0..=999 => tokens.to_string(),
+        1_000..=999_999 => format!("{:.1}k", tokens as f64 / 1_000.0),
+        _ => format!("{:.1}M", tokens as f64 / 1_000_000.0),
+    }
+}
+
+/// Git branches and token counts for the list, looked up once per scan (and
+/// only for columns the layout shows) instead of on every frame.
+#[derive(Debug, Default)]
+pub(crate) struct ColumnData {
+    /// Branch by working directory
+    pub(crate) branches: HashMap<String, Option<String>>,
+    /// Context tokens by agent PID
+    pub(crate) tokens: HashMap<u32, u64>,
+    /// Token count of each transcript as of its last modification time, so an
+    /// unchanged transcript is not read again
+    transcripts: HashMap<PathBuf, (std::time::SystemTime, Option<u64>)>,
+}
+
+impl ColumnData {
+    pub(crate) fn refresh(&mut self, sessions: &[AiSession], layout: &ListLayout) {
+        self.branches.clear();
+        if layout.shows(Column::Git) {
+            for session in sessions {
+                if !self.branches.contains_key(&session.working_dir) {
+                    let branch = git_branch(&session.working_dir);
+                    self.branches.insert(session.working_dir.clone(), branch);
+                }
+            }
+        }
+
+        self.tokens.clear();
+        if !layout.shows(Column::Tokens) {
+            self.transcripts.clear();
+            return;
+        }
+        let mut seen = HashSet::new();
+        for session in sessions {
+            let Some(path) = transcript_path(session) else {
+                continue;
+            };
+            let Ok(modified) = fs::metadata(&path).and_then(|m| m.modified()) else {
+                continue;
+            };
+            let tokens = match self.transcripts.get(&path) {
+                Some((cached_at, tokens)) if *cached_at == modified => *tokens,
+                _ => {
+                    let tokens = transcript_tokens(&path);
+                    self.transcripts.insert(path.clone(), (modified, tokens));
+                    tokens
+                }
+            };
+            if let Some(tokens) = tokens {
+                self.tokens.insert(session.pid, tokens);
+            }
+            seen.insert(path);
+        }
+        self.transcripts.retain(|path, _| seen.contains(path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcripts_give_the_latest_context_size() {
+        let claude = r#"{"type":"assistant","message":{"usage":{"input_tokens":444,"cache_creation_input_tokens":4096,"cache_read_input_tokens":104448,"output_tokens":705,"output_tokens_details":{"thinking_tokens":446}}}}"#;
+        let codex = r#"{"type":"event_msg","payload":{"type":"token_count","info":{"total_token_usage":{"total_tokens":90000},"last_token_usage":{"total_tokens":31250}}}}"#;
+        assert_eq!(line_tokens(claude), Some(109_693));
+        assert_eq!(line_tokens(codex), Some(31_250));
+        assert_eq!(
+            line_tokens(r#"{"type":"user","message":{"content":"hi"}}"#),
+            None
+        );
+
+        let path = env::temp_dir().join(format!("rpai-transcript-{}.jsonl", std::process::id()));
+        fs::write(
+            &path,
+            format!("{}\n{}\n{{\"type\":\"user\"}}\n", codex, claude),
+        )
+        .unwrap();
+        let tokens = transcript_tokens(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(tokens, Some(109_693));
+
+        assert_eq!(format_tokens(850), "850");
+        assert_eq!(format_tokens(109_693), "109.7k");
+        assert_eq!(format_tokens(1_250_000), "1.2M");
+    }
+}
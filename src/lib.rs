@@ -0,0 +1,52 @@
+//! Find AI coding agents (Claude Code, Codex, Gemini, OpenCode, ...) running
+//! on this machine, work out whether they are busy or waiting, and map them to
+//! the tmux, zellij or screen panes they run in.
+//!
+//! This is the library behind the `rpai` binary. Everything public is
+//! re-exported at the crate root; the modules themselves are private.
+//!
+//! ```no_run
+//! use rpai::{jump_to_session, Config, Scanner, SessionState};
+//!
+//! let sessions = Scanner::new(Config::load()).scan()?;
+//! if let Some(waiting) = sessions.iter().find(|s| s.state == SessionState::Waiting) {
+//!     jump_to_session(waiting, false)?;
+//! }
+//! # Ok::<(), rpai::Error>(())
+//! ```
+
+#![warn(missing_docs)]
+
+mod actions;
+mod backend;
+mod config;
+mod error;
+mod process;
+mod scanner;
+mod screen;
+mod session;
+mod tmux;
+mod zellij;
+
+pub use actions::{jump_to_session, jump_to_session_with, kill_session};
+#[cfg(any(test, feature = "test-util"))]
+pub use backend::{FakeMultiplexer, FakeProcesses};
+pub use backend::{Multiplexer, Pane, ProcessSource, Screen, SystemProcesses, Tmux, Zellij};
+pub use config::{Config, ReaperConfig};
+pub use error::{Error, Result};
+pub use process::{is_lsp_process, ProcessNode, ProcessRow, SessionDetails, LSP_PATTERNS};
+pub use scanner::Scanner;
+pub use session::{AiSession, ListeningPort, MultiplexerKind, SessionState};
+
+/// Helpers the `rpai` binary shares with the library. Not part of the API.
+#[doc(hidden)]
+pub mod support {
+    pub use crate::actions::{jump_to_session_recording_visit, kill_pid, signal_pid};
+    pub use crate::config::{config_dir, ensure_config_dir};
+    pub use crate::session::{format_duration, is_session_id, save_session_name, unix_now};
+    pub use crate::tmux::{
+        current_tmux_pane, focus_pane, get_tmux_pane_info, is_current_server, load_mru,
+        previous_visited_pane, record_visit, restore_zoom, run, run_on, same_server, shell_quote,
+        ServerPane,
+    };
+}
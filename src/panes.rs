@@ -0,0 +1,448 @@
+//! Pane actions: jumping, pulling, pushing, peeking and sending text.
+
+use crate::Result;
+use crossterm::event::{self, Event, KeyEventKind};
+use rpai::support::{
+    config_dir, current_tmux_pane, ensure_config_dir, focus_pane, get_tmux_pane_info,
+    is_current_server, load_mru, record_visit, restore_zoom, same_server, shell_quote, ServerPane,
+};
+use rpai::{AiSession, Config, Error};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io::stdout;
+use std::process::Command;
+use std::time::Duration;
+
+/// Copy `text` to the system clipboard: through tmux (which forwards it to the
+/// terminal) when running inside tmux, otherwise with the first clipboard tool found.
+pub(crate) fn copy_to_clipboard(text: &str) -> std::result::Result<(), String> {
+    if std::env::var_os("TMUX").is_some() {
+        return run_tmux(&["set-buffer", "-w", text]).map(|_| ());
+    }
+    let tools: &[(&str, &[&str])] = &[
+        ("pbcopy", &[]),
+        ("wl-copy", &[]),
+        ("xclip", &["-selection", "clipboard"]),
+        ("xsel", &["--clipboard", "--input"]),
+    ];
+    for (tool, args) in tools {
+        let Ok(mut child) = Command::new(tool)
+            .args(*args)
+            .stdin(std::process::Stdio::piped())
+            .spawn()
+        else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            use std::io::Write;
+            stdin
+                .write_all(text.as_bytes())
+                .map_err(|e| e.to_string())?;
+        }
+        return match child.wait() {
+            Ok(status) if status.success() => Ok(()),
+            _ => Err(format!("{} failed", tool)),
+        };
+    }
+    Err("No clipboard tool found (pbcopy, wl-copy, xclip or xsel)".to_string())
+}
+
+/// Show a desktop notification, ignoring failures.
+pub(crate) fn notify(title: &str, body: &str) {
+    if cfg!(target_os = "macos") {
+        let script = format!("display notification {:?} with title {:?}", body, title);
+        let _ = Command::new("osascript").args(["-e", &script]).output();
+    } else {
+        let _ = Command::new("notify-send").args([title, body]).output();
+    }
+}
+
+/// Type `text` into a session's tmux pane and press Enter.
+pub(crate) fn send_to_pane(session: &AiSession, text: &str) -> std::result::Result<(), String> {
+    let pane_id = session
+        .tmux_pane()
+        .ok_or_else(|| "Session is not in tmux".to_string())?;
+    let socket = session.mux_socket.as_deref();
+    run_tmux_on(socket, &["send-keys", "-t", pane_id, "-l", text])?;
+    run_tmux_on(socket, &["send-keys", "-t", pane_id, "Enter"])?;
+    Ok(())
+}
+
+/// Start an agent in a new background tmux window, returning the window target.
+pub(crate) fn new_agent_window(agent: &str, dir: &str) -> std::result::Result<String, String> {
+    let output = Command::new("tmux")
+        .args([
+            "new-window",
+            "-d",
+            "-P",
+            "-F",
+            "#{session_name}:#{window_index}",
+            "-c",
+            dir,
+            agent,
+        ])
+        .output()
+        .map_err(|e| format!("Failed to execute tmux new-window: {}", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(format!(
+            "tmux new-window failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// Run a tmux command, returning its trimmed stdout or a message built from its stderr.
+fn run_tmux(args: &[&str]) -> std::result::Result<String, String> {
+    rpai::support::run(args).map_err(|e| e.to_string())
+}
+
+/// [`run_tmux`] against the server at `socket`.
+pub(crate) fn run_tmux_on(
+    socket: Option<&str>,
+    args: &[&str],
+) -> std::result::Result<String, String> {
+    rpai::support::run_on(socket, args).map_err(|e| e.to_string())
+}
+
+pub(crate) fn jump_to_session(session: &AiSession) -> Result<()> {
+    let zoom = Config::load().zoom_on_jump;
+    match rpai::support::jump_to_session_recording_visit(session, zoom) {
+        Ok(()) => {
+            println!(
+                "Switched to session: {} (Window: {}, Pane: {})",
+                session.mux_session.as_deref().unwrap_or_default(),
+                session.mux_window.as_deref().unwrap_or("-"),
+                session.pane_id.as_deref().unwrap_or_default()
+            );
+            Ok(())
+        }
+        Err(Error::NotInMultiplexer) => {
+            Err("No tmux, zellij or screen session info available for this process".into())
+        }
+        Err(e) => Err(format!("Failed to jump to session: {}", e).into()),
+    }
+}
+
+/// Return to the most recently visited pane other than the current one. Only
+/// panes on the current server qualify, as the client can't switch servers.
+pub(crate) fn jump_back(config: &Config) -> Result<()> {
+    if env::var("TMUX").is_err() {
+        return Err("rpai back only works inside tmux".into());
+    }
+
+    let current = current_tmux_pane().map(|pane| ServerPane::new(None, &pane));
+    let live_panes: Vec<ServerPane> = get_tmux_pane_info(&config.tmux_sockets)
+        .unwrap_or_default()
+        .into_values()
+        .filter(|info| same_server(info.socket.as_deref(), None))
+        .map(|info| ServerPane::new(info.socket.as_deref(), &info.pane_id))
+        .collect();
+    let target = load_mru().into_iter().find(|p| {
+        Some(p) != current.as_ref() && live_panes.iter().any(|l| l.is(Some(&p.socket), &p.pane))
+    });
+
+    let Some(target) = target else {
+        return Err("No previous pane to go back to".into());
+    };
+
+    if let Some(current) = &current {
+        restore_zoom(current);
+    }
+
+    let pane = &target.pane;
+    match rpai::support::run(&["switch-client", "-t", pane])
+        .and_then(|_| focus_pane(None, pane, pane, false))
+    {
+        Ok(()) => {
+            record_visit(current.as_ref(), &target);
+            println!("Switched back to pane: {}", pane);
+            Ok(())
+        }
+        Err(e) => Err(format!("Failed to switch back to pane {}: {}", pane, e).into()),
+    }
+}
+
+/// Where a pulled pane came from, so `rpai push` can send it back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PulledPane {
+    /// Socket of the pane's server (see [`ServerPane`])
+    socket: String,
+    pane_id: String,
+    session_name: String,
+    window_id: String,
+    window_index: u32,
+}
+
+fn load_pulled_panes() -> Vec<PulledPane> {
+    fs::read_to_string(config_dir().join("pulled.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_pulled_panes(panes: &[PulledPane]) -> std::result::Result<(), String> {
+    let dir = ensure_config_dir().map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(panes).map_err(|e| e.to_string())?;
+    fs::write(dir.join("pulled.json"), content).map_err(|e| e.to_string())
+}
+
+/// Join `pane_id` into the current window next to the focused pane, split
+/// horizontally (side by side) or vertically, remembering its original window.
+pub(crate) fn pull_pane(
+    session: &AiSession,
+    horizontal: bool,
+) -> std::result::Result<String, String> {
+    let pane_id = session
+        .tmux_pane()
+        .ok_or_else(|| "No tmux session info available for this process".to_string())?;
+    let current =
+        current_tmux_pane().ok_or_else(|| "rpai pull only works inside tmux".to_string())?;
+    if !is_current_server(session.mux_socket.as_deref()) {
+        return Err(format!(
+            "Pane {} is on another tmux server ({})",
+            pane_id,
+            session.location().unwrap_or_default()
+        ));
+    }
+    let here = run_tmux(&["display-message", "-p", "-t", &current, "#{window_id}"])?;
+    let origin = run_tmux(&[
+        "display-message",
+        "-p",
+        "-t",
+        pane_id,
+        "#{session_name}\t#{window_id}\t#{window_index}",
+    ])?;
+    let parts: Vec<&str> = origin.split('\t').collect();
+    if parts.len() < 3 {
+        return Err(format!("Unexpected tmux output: {}", origin));
+    }
+    if parts[1] == here {
+        return Err(format!("Pane {} is already in this window", pane_id));
+    }
+
+    let split = if horizontal { "-h" } else { "-v" };
+    run_tmux(&["join-pane", "-d", split, "-s", pane_id, "-t", &current])?;
+
+    let pane = ServerPane::new(session.mux_socket.as_deref(), pane_id);
+    let mut pulled = load_pulled_panes();
+    pulled.retain(|p| !pane.is(Some(&p.socket), &p.pane_id));
+    pulled.push(PulledPane {
+        socket: pane.socket,
+        pane_id: pane.pane,
+        session_name: parts[0].to_string(),
+        window_id: parts[1].to_string(),
+        window_index: parts[2].parse().unwrap_or(0),
+    });
+    save_pulled_panes(&pulled)?;
+
+    Ok(format!("Pulled {} from {}:{}", pane_id, parts[0], parts[2]))
+}
+
+/// The pulled pane `rpai push` sends back.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum PushTarget<'a> {
+    /// The pane of this session, which has to be in tmux
+    Session(&'a AiSession),
+    /// The most recently pulled pane in the current window
+    CurrentWindow,
+}
+
+/// Send a pulled pane back to its original window, or to a new window at the
+/// original index if that window closed when the pane left it.
+pub(crate) fn push_pane(target: PushTarget) -> std::result::Result<String, String> {
+    let mut pulled = load_pulled_panes();
+    let idx = match target {
+        PushTarget::Session(session) => {
+            let pane_id = session
+                .tmux_pane()
+                .ok_or_else(|| "Session is not in tmux".to_string())?;
+            let pane = ServerPane::new(session.mux_socket.as_deref(), pane_id);
+            pulled
+                .iter()
+                .position(|p| pane.is(Some(&p.socket), &p.pane_id))
+                .ok_or_else(|| format!("Pane {} was not pulled by rpai", pane_id))?
+        }
+        PushTarget::CurrentWindow => {
+            let here = current_tmux_pane()
+                .and_then(|p| run_tmux(&["display-message", "-p", "-t", &p, "#{window_id}"]).ok())
+                .ok_or_else(|| "rpai push only works inside tmux".to_string())?;
+            pulled
+                .iter()
+                .rposition(|p| {
+                    same_server(Some(&p.socket), None)
+                        && run_tmux(&["display-message", "-p", "-t", &p.pane_id, "#{window_id}"])
+                            .is_ok_and(|w| w == here)
+                })
+                .ok_or_else(|| "No pulled pane in this window".to_string())?
+        }
+    };
+    let entry = pulled.remove(idx);
+
+    let window_exists = run_tmux(&[
+        "display-message",
+        "-p",
+        "-t",
+        &entry.window_id,
+        "#{window_id}",
+    ])
+    .is_ok_and(|w| w == entry.window_id);
+    let result = if window_exists {
+        run_tmux(&[
+            "join-pane",
+            "-d",
+            "-s",
+            &entry.pane_id,
+            "-t",
+            &entry.window_id,
+        ])
+    } else {
+        let target = format!("{}:{}", entry.session_name, entry.window_index);
+        run_tmux(&["break-pane", "-d", "-s", &entry.pane_id, "-t", &target])
+            // The index may have been taken since; fall back to the next free one
+            .or_else(|_| {
+                let session = format!("{}:", entry.session_name);
+                run_tmux(&["break-pane", "-d", "-s", &entry.pane_id, "-t", &session])
+            })
+    };
+
+    // A pane that no longer exists can't be pushed; forget it either way
+    save_pulled_panes(&pulled)?;
+    result?;
+    Ok(format!(
+        "Pushed {} back to {}:{}",
+        entry.pane_id, entry.session_name, entry.window_index
+    ))
+}
+
+const PEEK_REFRESH_MS: u64 = 250;
+
+pub(crate) fn peek_title(session: &AiSession) -> String {
+    let location = match (&session.mux_session, &session.mux_window) {
+        (Some(name), Some(window)) => format!("{}:{}", name, window),
+        _ => "-".to_string(),
+    };
+    let pane = session.pane_id.as_deref().unwrap_or("-");
+    format!(
+        "{} {} ({})",
+        session.agent_type,
+        session.name.as_deref().unwrap_or(&location),
+        match session.mux_server() {
+            Some(server) => format!("{} @{}", pane, server),
+            None => pane.to_string(),
+        }
+    )
+}
+
+/// Show a live, read-only view of a pane by re-capturing it until a key is
+/// pressed. Expects the terminal to be in raw mode on the alternate screen.
+pub(crate) fn peek_pane(socket: Option<&str>, pane_id: &str, title: &str) -> Result<()> {
+    use crossterm::{
+        cursor::MoveTo,
+        queue,
+        style::Print,
+        terminal::{Clear, ClearType},
+    };
+    use std::io::Write;
+
+    let mut out = stdout();
+    // Long lines would wrap and scroll the view, so disable autowrap meanwhile
+    queue!(out, Print("\x1b[?7l"))?;
+
+    let result = (|| -> Result<()> {
+        loop {
+            let (_, rows) = crossterm::terminal::size()?;
+            let content = run_tmux_on(socket, &["capture-pane", "-p", "-e", "-t", pane_id])?;
+            let mut lines: Vec<&str> = content.lines().collect();
+            while lines.last().is_some_and(|l| l.trim().is_empty()) {
+                lines.pop();
+            }
+            let visible = (rows as usize).saturating_sub(1);
+            let start = lines.len().saturating_sub(visible);
+
+            queue!(
+                out,
+                MoveTo(0, 0),
+                Clear(ClearType::All),
+                Print(format!(
+                    "\x1b[7m peek: {}  (any key to close) \x1b[0m",
+                    title
+                ))
+            )?;
+            for (i, line) in lines[start..].iter().enumerate() {
+                queue!(out, MoveTo(0, i as u16 + 1), Print(line), Print("\x1b[0m"))?;
+            }
+            out.flush()?;
+
+            if event::poll(Duration::from_millis(PEEK_REFRESH_MS))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    })();
+
+    queue!(out, Print("\x1b[?7h"), Clear(ClearType::All))?;
+    out.flush()?;
+    result
+}
+
+/// Open a tmux popup sized to the session's pane that peeks at it.
+pub(crate) fn open_peek_popup(session: &AiSession) -> std::result::Result<(), String> {
+    let Some(pane_id) = session.tmux_pane() else {
+        return Err("No tmux session info available for this process".to_string());
+    };
+    if env::var("TMUX").is_err() {
+        return Err("rpai peek only works inside tmux".to_string());
+    }
+
+    // Pane size plus the popup border and title line, clamped to the client
+    let client = run_tmux(&["display-message", "-p", "#{client_width} #{client_height}"])
+        .unwrap_or_default();
+    let mut client_size = client
+        .split_whitespace()
+        .filter_map(|v| v.parse::<u32>().ok());
+    let (client_width, client_height) = (
+        client_size.next().unwrap_or(u32::MAX),
+        client_size.next().unwrap_or(u32::MAX),
+    );
+    let width = (session.pane_width.unwrap_or(80) + 2).min(client_width);
+    let height = (session.pane_height.unwrap_or(24) + 3).min(client_height);
+
+    let exe = env::current_exe()
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|_| "rpai".to_string());
+    let mut command = format!(
+        "{} peek-pane {} {}",
+        shell_quote(&exe),
+        shell_quote(pane_id),
+        shell_quote(&peek_title(session))
+    );
+    if let Some(socket) = &session.mux_socket {
+        command.push(' ');
+        command.push_str(&shell_quote(socket));
+    }
+
+    run_tmux(&[
+        "display-popup",
+        "-E",
+        "-w",
+        &width.to_string(),
+        "-h",
+        &height.to_string(),
+        &command,
+    ])
+    .map(|_| ())
+    .map_err(|e| format!("Failed to open peek popup: {}", e))
+}
+
+/// Whether rpai runs in a tmux pane, where it can open popups. tmux silently
+/// ignores a popup opened from inside another one, and popups (such as the
+/// recommended `display-popup -E rpai`) have no `TMUX_PANE`.
+pub(crate) fn in_tmux_pane() -> bool {
+    env::var_os("TMUX").is_some() && env::var_os("TMUX_PANE").is_some()
+}
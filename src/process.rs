@@ -0,0 +1,743 @@
+//! Process inspection: the process tree below an agent and what it says about
+//! the agent's state, activity, memory and listening ports.
+
+use crate::backend::{find_pane_for_pid, Pane, ProcessSource};
+use crate::error::{Error, Result};
+use crate::session::{format_duration, ListeningPort, SessionState};
+use std::collections::HashMap;
+use std::fs;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// LSP server patterns to exclude from CPU calculation.
+/// These run in the background and don't indicate the AI agent is actively working.
+pub const LSP_PATTERNS: &[&str] = &[
+    "pyright-langserver",
+    "pylsp",
+    "python-lsp-server",
+    "clangd",
+    "typescript-language-server",
+    "tsserver",
+    "gopls",
+    "rust-analyzer",
+    "lua-language-server",
+    "bash-language-server",
+    "vscode-json-language",
+    "yaml-language-server",
+    "tailwindcss-language-server",
+];
+
+/// Whether a command line belongs to an LSP server (see [`LSP_PATTERNS`]).
+pub fn is_lsp_process(command: &str) -> bool {
+    let cmd_lower = command.to_lowercase();
+    LSP_PATTERNS
+        .iter()
+        .any(|pattern| cmd_lower.contains(pattern))
+}
+
+/// CPU usage of the agent and its descendants, leaving out LSP servers.
+fn process_tree_cpu_usage(agent: &ProcessRow, tree: &[ProcessNode]) -> f64 {
+    let descendants: f64 = tree
+        .iter()
+        .filter(|n| !n.is_lsp)
+        .map(|n| n.row.cpu_percent)
+        .sum();
+    agent.cpu_percent + descendants
+}
+
+pub(crate) fn get_session_state_and_cpu(
+    agent: &ProcessRow,
+    tree: &[ProcessNode],
+    idle_threshold: f64,
+) -> (SessionState, f64) {
+    // Check CPU usage of the AI agent process and all its descendants
+    // (LSP servers are filtered out in process_tree_cpu_usage)
+    let cpu_pct = process_tree_cpu_usage(agent, tree);
+
+    // Use CPU as the primary signal for determining state
+    if cpu_pct > idle_threshold {
+        (SessionState::Running, cpu_pct)
+    } else {
+        (SessionState::Waiting, cpu_pct)
+    }
+}
+
+/// Shortest time CPU use is measured over. CPU time advances in 10 ms ticks,
+/// so over a 50 ms refresh a single tick would read as 20%.
+const MIN_CPU_WINDOW: Duration = Duration::from_secs(1);
+
+/// CPU use measured between scans from each process's CPU time. `ps` only
+/// reports the average since a process started, which hides an agent that
+/// has just gone idle after a long busy stretch, or vice versa.
+#[derive(Debug, Default)]
+pub(crate) struct CpuSampler {
+    last: HashMap<u32, CpuSample>,
+}
+
+/// Where the current measurement window of a process started, and the
+/// figure of the last window that ended.
+#[derive(Debug)]
+struct CpuSample {
+    cpu_time: Duration,
+    at: Instant,
+    percent: Option<f64>,
+}
+
+impl CpuSampler {
+    /// CPU use of `row` over the last window of at least [`MIN_CPU_WINDOW`],
+    /// given its current CPU time. Falls back to `ps`'s figure until the
+    /// first window ends, or when the source has no CPU time.
+    pub(crate) fn percent(&mut self, row: &ProcessRow, cpu_time: Option<Duration>) -> f64 {
+        self.percent_at(row, cpu_time, Instant::now())
+    }
+
+    fn percent_at(&mut self, row: &ProcessRow, cpu_time: Option<Duration>, now: Instant) -> f64 {
+        let Some(cpu_time) = cpu_time else {
+            return row.cpu_percent;
+        };
+        match self.last.get_mut(&row.pid) {
+            // A lower CPU time means the PID was reused
+            Some(sample) if cpu_time >= sample.cpu_time => {
+                let window = now.saturating_duration_since(sample.at);
+                if window >= MIN_CPU_WINDOW {
+                    let used = (cpu_time - sample.cpu_time).as_secs_f64();
+                    let percent = 100.0 * used / window.as_secs_f64();
+                    *sample = CpuSample {
+                        cpu_time,
+                        at: now,
+                        percent: Some(percent),
+                    };
+                }
+                sample.percent.unwrap_or(row.cpu_percent)
+            }
+            _ => {
+                let sample = CpuSample {
+                    cpu_time,
+                    at: now,
+                    percent: None,
+                };
+                self.last.insert(row.pid, sample);
+                row.cpu_percent
+            }
+        }
+    }
+
+    /// Forget processes that have exited.
+    pub(crate) fn retain(&mut self, rows: &HashMap<u32, ProcessRow>) {
+        self.last.retain(|pid, _| rows.contains_key(pid));
+    }
+}
+
+/// User plus system CPU time of a process, from /proc/<pid>/stat.
+pub(crate) fn read_cpu_time(pid: u32) -> Option<Duration> {
+    // /proc reports times in USER_HZ, which is 100 on every Linux platform
+    const TICKS_PER_SECOND: u64 = 100;
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let rest = &stat[stat.rfind(')')? + 1..];
+    let mut fields = rest.split_whitespace().skip(11);
+    let utime: u64 = fields.next()?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    Some(Duration::from_millis(
+        (utime + stime) * 1000 / TICKS_PER_SECOND,
+    ))
+}
+
+pub(crate) fn get_cwd_via_lsof(pid: u32) -> Option<String> {
+    let output = Command::new("lsof")
+        .args(["-p", &pid.to_string(), "-a"])
+        .output()
+        .ok()?;
+
+    if output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        for line in stdout.lines() {
+            if line.contains(" cwd ") {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if let Some(path) = parts.last() {
+                    return Some(path.to_string());
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// A process as listed by `ps`.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ProcessRow {
+    /// Process id
+    pub pid: u32,
+    /// Parent process id
+    pub ppid: u32,
+    /// Executable name (`ps -o comm`)
+    pub comm: String,
+    /// CPU usage as reported by `ps`; the scanner replaces it with the use
+    /// since its previous scan for the processes of agent trees
+    pub cpu_percent: f64,
+    /// Resident memory in KB
+    pub rss_kb: u64,
+    /// Controlling terminal, or "?"
+    pub tty: String,
+    /// Start time, e.g. "Sat Oct 18 09:12:01 2026"
+    pub started: String,
+    /// Seconds since the process started
+    pub elapsed_seconds: i64,
+    /// Full command line
+    pub command: String,
+}
+
+/// Split off `n` whitespace-separated fields, returning them and the rest of the line.
+fn split_fields(line: &str, n: usize) -> Option<(Vec<&str>, &str)> {
+    let mut rest = line.trim_start();
+    let mut fields = Vec::with_capacity(n);
+    for _ in 0..n {
+        if rest.is_empty() {
+            return None;
+        }
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    Some((fields, rest))
+}
+
+pub(crate) fn get_process_rows() -> Result<HashMap<u32, ProcessRow>> {
+    let output = Command::new("ps")
+        .args(["-axo", "pid=,ppid=,pcpu=,rss=,tty=,etime=,lstart=,command="])
+        .output()
+        .map_err(|e| Error::Command {
+            command: "ps".to_string(),
+            message: e.to_string(),
+        })?;
+
+    let mut rows = HashMap::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        // lstart is five fields, e.g. "Sat Oct 18 09:12:01 2026"
+        let Some((fields, command)) = split_fields(line, 11) else {
+            continue;
+        };
+        let Ok(pid) = fields[0].parse::<u32>() else {
+            continue;
+        };
+        rows.insert(
+            pid,
+            ProcessRow {
+                pid,
+                ppid: fields[1].parse().unwrap_or(0),
+                comm: String::new(),
+                cpu_percent: fields[2].parse().unwrap_or(0.0),
+                rss_kb: fields[3].parse().unwrap_or(0),
+                tty: fields[4].to_string(),
+                elapsed_seconds: parse_etime(fields[5]).unwrap_or(0),
+                started: fields[6..11].join(" "),
+                command: command.to_string(),
+            },
+        );
+    }
+
+    // comm can contain spaces, so it gets its own call with nothing after it
+    if let Ok(output) = Command::new("ps").args(["-axo", "pid=,comm="]).output() {
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let Some((pid, comm)) = line.trim_start().split_once(char::is_whitespace) else {
+                continue;
+            };
+            if let Some(row) = pid.parse().ok().and_then(|pid: u32| rows.get_mut(&pid)) {
+                row.comm = comm.trim().to_string();
+            }
+        }
+    }
+    Ok(rows)
+}
+
+/// Parse `ps` elapsed time, formatted `[[dd-]hh:]mm:ss`, into seconds.
+fn parse_etime(etime: &str) -> Option<i64> {
+    let (days, clock) = match etime.split_once('-') {
+        Some((days, clock)) => (days.parse::<i64>().ok()?, clock),
+        None => (0, etime),
+    };
+    let mut seconds = 0;
+    for part in clock.split(':') {
+        seconds = seconds * 60 + part.parse::<i64>().ok()?;
+    }
+    Some(days * 86400 + seconds)
+}
+
+/// A descendant process, `depth` levels below the agent.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ProcessNode {
+    /// 1 for the agent's children, 2 for grandchildren, ...
+    pub depth: usize,
+    /// The process itself
+    pub row: ProcessRow,
+    /// LSP servers are left out of the session's CPU and memory figures
+    pub is_lsp: bool,
+}
+
+/// Everything known about one session's processes.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SessionDetails {
+    /// PID of the agent
+    pub pid: u32,
+    /// The agent process, unless it has exited
+    pub process: Option<ProcessRow>,
+    /// The agent and its ancestors up to the pane's process
+    pub ancestry: Vec<ProcessRow>,
+    /// The pane the agent runs in
+    pub pane: Option<Pane>,
+    /// Descendants in depth-first order
+    pub descendants: Vec<ProcessNode>,
+    /// Proportional set size of the agent and its non-LSP descendants, where
+    /// `/proc/<pid>/smaps_rollup` exists
+    pub pss_mb: Option<u64>,
+}
+
+/// The children of every process, sorted by PID.
+pub(crate) fn child_index(rows: &HashMap<u32, ProcessRow>) -> HashMap<u32, Vec<u32>> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for row in rows.values() {
+        children.entry(row.ppid).or_default().push(row.pid);
+    }
+    for kids in children.values_mut() {
+        kids.sort();
+    }
+    children
+}
+
+/// Depth-first list of the descendants of `pid`, children sorted by PID.
+pub(crate) fn process_tree(pid: u32, rows: &HashMap<u32, ProcessRow>) -> Vec<ProcessNode> {
+    descendants(pid, rows, &child_index(rows))
+}
+
+/// [`process_tree`] with the [`child_index`] of `rows`, which a scan builds once
+/// for all agents.
+pub(crate) fn descendants(
+    pid: u32,
+    rows: &HashMap<u32, ProcessRow>,
+    children: &HashMap<u32, Vec<u32>>,
+) -> Vec<ProcessNode> {
+    let mut nodes = Vec::new();
+    let mut stack: Vec<(u32, usize)> = children
+        .get(&pid)
+        .map(|kids| kids.iter().rev().map(|&k| (k, 1)).collect())
+        .unwrap_or_default();
+    while let Some((current, depth)) = stack.pop() {
+        if let Some(row) = rows.get(&current) {
+            nodes.push(ProcessNode {
+                depth,
+                is_lsp: is_lsp_process(&row.command),
+                row: row.clone(),
+            });
+        }
+        if let Some(kids) = children.get(&current) {
+            stack.extend(kids.iter().rev().map(|&k| (k, depth + 1)));
+        }
+    }
+    nodes
+}
+
+/// Proportional set size of a process in KB, from /proc/<pid>/smaps_rollup.
+pub(crate) fn read_pss_kb(pid: u32) -> Option<u64> {
+    let content = fs::read_to_string(format!("/proc/{}/smaps_rollup", pid)).ok()?;
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("Pss:"))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|kb| kb.parse().ok())
+}
+
+/// Environment variables of a process named in `names`, from /proc/<pid>/environ.
+fn read_environ(pid: u32, names: &[&str]) -> HashMap<String, String> {
+    let Ok(environ) = fs::read(format!("/proc/{}/environ", pid)) else {
+        return HashMap::new();
+    };
+    environ
+        .split(|&b| b == 0)
+        .filter_map(|var| {
+            let var = String::from_utf8_lossy(var);
+            let (name, value) = var.split_once('=')?;
+            names
+                .contains(&name)
+                .then(|| (name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// The children of processes whose executable is named `server` (e.g.
+/// "zellij") in the process table `rows`, with the variables named in `vars`
+/// from their environment, keyed by PID. Only the children's environments are
+/// read, so nothing is read when no such server runs. Empty where there is no /proc.
+pub(crate) fn server_children_env(
+    server: &str,
+    vars: &[&str],
+    rows: &HashMap<u32, ProcessRow>,
+) -> HashMap<u32, HashMap<String, String>> {
+    let is_server = |pid: &u32| {
+        rows.get(pid).is_some_and(|row| {
+            let name = row.comm.rsplit('/').next().unwrap_or(&row.comm);
+            name.eq_ignore_ascii_case(server)
+        })
+    };
+    rows.values()
+        .filter(|row| is_server(&row.ppid))
+        .map(|row| (row.pid, read_environ(row.pid, vars)))
+        .collect()
+}
+
+/// Memory of the agent and its descendants `nodes` in MB: non-LSP RSS and LSP RSS.
+pub(crate) fn session_memory(agent: &ProcessRow, nodes: &[ProcessNode]) -> (u64, u64) {
+    let (lsp, tree): (Vec<&ProcessNode>, Vec<&ProcessNode>) = nodes.iter().partition(|n| n.is_lsp);
+    let rss_kb: u64 = agent.rss_kb + tree.iter().map(|n| n.row.rss_kb).sum::<u64>();
+    let lsp_kb: u64 = lsp.iter().map(|n| n.row.rss_kb).sum();
+    (rss_kb / 1024, lsp_kb / 1024)
+}
+
+/// PSS of the agent `pid` and its non-LSP descendants `nodes` in MB, when
+/// readable for every process. Reading smaps_rollup is slow, so only the
+/// detail view asks for it.
+fn session_pss_mb(pid: u32, nodes: &[ProcessNode], source: &dyn ProcessSource) -> Option<u64> {
+    let pids = std::iter::once(pid).chain(nodes.iter().filter(|n| !n.is_lsp).map(|n| n.row.pid));
+    let pss_kb: Option<u64> = pids.map(|pid| source.pss_kb(pid)).sum();
+    pss_kb.map(|kb| kb / 1024)
+}
+
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "fish", "dash"];
+
+/// The command with its program path reduced to the file name.
+fn short_command(command: &str) -> String {
+    let (program, args) = command.split_once(' ').unwrap_or((command, ""));
+    let program = program.rsplit('/').next().unwrap_or(program);
+    format!("{} {}", program, args).trim().to_string()
+}
+
+fn is_shell(command: &str) -> bool {
+    let program = command.split_whitespace().next().unwrap_or("");
+    let program = program.rsplit('/').next().unwrap_or(program);
+    SHELLS.contains(&program.trim_start_matches('-'))
+}
+
+/// Describe what a running agent is doing from its descendants `tree`: the
+/// busiest non-LSP task among its children (looking through shell wrappers), or
+/// "thinking" when only the agent itself is busy. Waiting agents have no activity.
+pub(crate) fn session_activity(state: SessionState, tree: &[ProcessNode]) -> Option<String> {
+    if state != SessionState::Running {
+        return None;
+    }
+    let nodes: Vec<&ProcessNode> = tree.iter().filter(|n| !n.is_lsp).collect();
+
+    // Each child of the agent is one task, named after the first non-shell
+    // process in its subtree
+    let mut tasks: Vec<(f64, &ProcessRow)> = Vec::new();
+    for (i, node) in nodes.iter().enumerate() {
+        if node.depth != 1 {
+            continue;
+        }
+        let subtree: Vec<&ProcessNode> = std::iter::once(*node)
+            .chain(nodes[i + 1..].iter().copied().take_while(|n| n.depth > 1))
+            .collect();
+        let cpu: f64 = subtree.iter().map(|n| n.row.cpu_percent).sum();
+        let task = subtree
+            .iter()
+            .find(|n| !is_shell(&n.row.command))
+            .unwrap_or(node);
+        tasks.push((cpu, &task.row));
+    }
+
+    let busiest = tasks
+        .into_iter()
+        .filter(|(cpu, _)| *cpu > 0.0)
+        .max_by(|a, b| {
+            a.0.partial_cmp(&b.0)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.1.elapsed_seconds.cmp(&b.1.elapsed_seconds))
+        });
+    Some(match busiest {
+        Some((_, row)) => format!(
+            "running: {} ({})",
+            short_command(&row.command),
+            format_duration(row.elapsed_seconds)
+        ),
+        None => "thinking".to_string(),
+    })
+}
+
+/// Details of the session whose agent is `pid`, from one read of the process
+/// table and panes.
+pub(crate) fn session_details(
+    pid: u32,
+    rows: &HashMap<u32, ProcessRow>,
+    panes: &HashMap<u32, Pane>,
+    source: &dyn ProcessSource,
+) -> SessionDetails {
+    let (ancestry, pane) = match find_pane_for_pid(pid, rows, panes) {
+        Some((chain, pane)) => (
+            chain.iter().filter_map(|p| rows.get(p).cloned()).collect(),
+            Some(pane),
+        ),
+        None => (Vec::new(), None),
+    };
+
+    let descendants = process_tree(pid, rows);
+    SessionDetails {
+        pid,
+        process: rows.get(&pid).cloned(),
+        ancestry,
+        pane,
+        pss_mb: rows
+            .contains_key(&pid)
+            .then(|| session_pss_mb(pid, &descendants, source))
+            .flatten(),
+        descendants,
+    }
+}
+
+const INTERPRETERS: &[&str] = &[
+    "node", "python", "bun", "deno", "ruby", "java", "php", "perl", "npm", "pnpm", "yarn",
+];
+
+/// Subcommands through which package managers and runtimes run a script:
+/// `bun run dev.js` and `npm run dev` are named after the script.
+const RUNNER_SUBCOMMANDS: &[&str] = &["run", "x", "exec", "start"];
+
+/// Whether `program` is `interpreter`, allowing a version suffix
+/// (`python3.12`) but not another program sharing its prefix (`nodemon`).
+fn is_interpreter(program: &str, interpreter: &str) -> bool {
+    program
+        .strip_prefix(interpreter)
+        .is_some_and(|version| version.chars().all(|c| c.is_ascii_digit() || c == '.'))
+}
+
+/// Name a server after its program, or after the script an interpreter runs
+/// (`node .../node_modules/.bin/vite` is "vite").
+fn server_name(command: &str) -> String {
+    let mut args = command.split_whitespace();
+    let program = args.next().unwrap_or("");
+    let program = program.rsplit('/').next().unwrap_or(program);
+    if INTERPRETERS.iter().any(|i| is_interpreter(program, i)) {
+        let mut scripts = args.filter(|a| !a.starts_with('-')).peekable();
+        if scripts
+            .peek()
+            .is_some_and(|a| RUNNER_SUBCOMMANDS.contains(a))
+        {
+            scripts.next();
+        }
+        if let Some(script) = scripts.next() {
+            let script = script.rsplit('/').next().unwrap_or(script);
+            let script = script
+                .strip_suffix(".js")
+                .or_else(|| script.strip_suffix(".mjs"))
+                .or_else(|| script.strip_suffix(".py"))
+                .unwrap_or(script);
+            return script.to_string();
+        }
+    }
+    program.to_string()
+}
+
+/// The socket inode and local port of a /proc/net/tcp{,6} line in LISTEN state.
+#[cfg(target_os = "linux")]
+fn parse_tcp_listen(line: &str) -> Option<(&str, u16)> {
+    const TCP_LISTEN: &str = "0A";
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 10 || fields[3] != TCP_LISTEN {
+        return None;
+    }
+    let port = u16::from_str_radix(fields[1].rsplit(':').next()?, 16).ok()?;
+    Some((fields[9], port))
+}
+
+/// Listening ports owned by any of `pids`: socket inodes from /proc/net/tcp{,6}
+/// matched against each process's fd links.
+#[cfg(target_os = "linux")]
+pub(crate) fn listening_ports_for(pids: &[u32]) -> Vec<(u32, u16)> {
+    let mut inode_ports: HashMap<String, u16> = HashMap::new();
+    for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        let Ok(content) = fs::read_to_string(table) else {
+            continue;
+        };
+        for line in content.lines().skip(1) {
+            if let Some((inode, port)) = parse_tcp_listen(line) {
+                inode_ports.insert(inode.to_string(), port);
+            }
+        }
+    }
+    if inode_ports.is_empty() {
+        return Vec::new();
+    }
+
+    let mut found = Vec::new();
+    for &pid in pids {
+        let Ok(fds) = fs::read_dir(format!("/proc/{}/fd", pid)) else {
+            continue;
+        };
+        for fd in fds.flatten() {
+            let Ok(link) = fs::read_link(fd.path()) else {
+                continue;
+            };
+            let link = link.to_string_lossy();
+            if let Some(inode) = link
+                .strip_prefix("socket:[")
+                .and_then(|l| l.strip_suffix(']'))
+            {
+                if let Some(&port) = inode_ports.get(inode) {
+                    found.push((pid, port));
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Listening ports owned by any of `pids`, via lsof where /proc is unavailable.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn listening_ports_for(pids: &[u32]) -> Vec<(u32, u16)> {
+    let pid_list: Vec<String> = pids.iter().map(|p| p.to_string()).collect();
+    let Ok(output) = Command::new("lsof")
+        .args([
+            "-nP",
+            "-a",
+            "-p",
+            &pid_list.join(","),
+            "-iTCP",
+            "-sTCP:LISTEN",
+            "-Fpn",
+        ])
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    let mut found = Vec::new();
+    let mut pid = 0;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Some(p) = line.strip_prefix('p') {
+            pid = p.parse().unwrap_or(0);
+        } else if let Some(name) = line.strip_prefix('n') {
+            if let Some(port) = name.rsplit(':').next().and_then(|p| p.parse().ok()) {
+                found.push((pid, port));
+            }
+        }
+    }
+    found
+}
+
+/// Listening ports of the agent `pid` and its descendants `tree`, sorted by
+/// port, out of the (pid, port) pairs a scan found for every agent.
+pub(crate) fn session_ports(
+    pid: u32,
+    tree: &[ProcessNode],
+    rows: &HashMap<u32, ProcessRow>,
+    listening: &[(u32, u16)],
+) -> Vec<ListeningPort> {
+    let mut pids = vec![pid];
+    pids.extend(tree.iter().map(|n| n.row.pid));
+
+    let mut ports: Vec<ListeningPort> = listening
+        .iter()
+        .filter(|(pid, _)| pids.contains(pid))
+        .map(|&(pid, port)| ListeningPort {
+            port,
+            pid,
+            name: rows
+                .get(&pid)
+                .map(|r| server_name(&r.command))
+                .unwrap_or_else(|| "?".to_string()),
+        })
+        .collect();
+    // IPv4 and IPv6 sockets on the same port are one server
+    ports.sort_by_key(|p| (p.port, p.pid));
+    ports.dedup_by_key(|p| p.port);
+    ports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_use_is_measured_over_at_least_a_second() {
+        let row = ProcessRow {
+            pid: 200,
+            ppid: 1,
+            comm: "claude".to_string(),
+            cpu_percent: 1.0,
+            rss_kb: 0,
+            tty: "?".to_string(),
+            started: String::new(),
+            elapsed_seconds: 0,
+            command: "claude".to_string(),
+        };
+        let ms = Duration::from_millis;
+        let start = Instant::now();
+        let mut sampler = CpuSampler::default();
+        assert_eq!(sampler.percent_at(&row, Some(ms(0)), start), 1.0);
+        // One 10 ms tick between 50 ms refreshes is not 20%
+        assert_eq!(sampler.percent_at(&row, Some(ms(10)), start + ms(50)), 1.0);
+        assert_eq!(sampler.percent_at(&row, Some(ms(10)), start + ms(100)), 1.0);
+        let percent = sampler.percent_at(&row, Some(ms(100)), start + ms(1000));
+        assert!((percent - 10.0).abs() < 1e-9, "{}", percent);
+        // The figure holds until the next window ends
+        assert_eq!(
+            sampler.percent_at(&row, Some(ms(110)), start + ms(1050)),
+            percent
+        );
+    }
+
+    #[test]
+    fn parses_ps_elapsed_times() {
+        assert_eq!(parse_etime("00:07"), Some(7));
+        assert_eq!(parse_etime("12:34"), Some(12 * 60 + 34));
+        assert_eq!(parse_etime("01:02:03"), Some(3723));
+        assert_eq!(
+            parse_etime("3-04:05:06"),
+            Some(3 * 86400 + 4 * 3600 + 5 * 60 + 6)
+        );
+        assert_eq!(parse_etime(""), None);
+        assert_eq!(parse_etime("1:xx"), None);
+        assert_eq!(parse_etime("x-01:00"), None);
+    }
+
+    #[test]
+    fn servers_are_named_after_their_program_or_script() {
+        assert_eq!(
+            server_name("/usr/bin/python3 -m http.server 8000"),
+            "http.server"
+        );
+        assert_eq!(
+            server_name("node /src/web/node_modules/.bin/vite --port 5173"),
+            "vite"
+        );
+        assert_eq!(server_name("node --inspect server.mjs"), "server");
+        assert_eq!(server_name("bun run dev.js"), "dev");
+        assert_eq!(server_name("npm run dev"), "dev");
+        assert_eq!(server_name("/usr/bin/python3.12 app.py"), "app");
+        assert_eq!(server_name("nodemon server.js"), "nodemon");
+        assert_eq!(server_name("phpstan analyse"), "phpstan");
+        assert_eq!(
+            server_name("/usr/local/bin/redis-server *:6379"),
+            "redis-server"
+        );
+        assert_eq!(server_name("node"), "node");
+        assert_eq!(server_name(""), "");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn reads_listening_sockets_from_proc_net_tcp() {
+        let listen = "   1: 00000000:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 123456 1 0000000000000000 100 0 0 10 0";
+        assert_eq!(parse_tcp_listen(listen), Some(("123456", 8080)));
+
+        let listen6 = "   0: 00000000000000000000000000000000:1435 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 98765 1 0000000000000000 100 0 0 10 0";
+        assert_eq!(parse_tcp_listen(listen6), Some(("98765", 5173)));
+
+        let established = "   2: 0100007F:1F90 0100007F:C350 01 00000000:00000000 00:00000000 00000000  1000        0 123457 1 0000000000000000 20 4 30 10 -1";
+        assert_eq!(parse_tcp_listen(established), None);
+        assert_eq!(
+            parse_tcp_listen("  sl  local_address rem_address   st"),
+            None
+        );
+    }
+}
@@ -0,0 +1,368 @@
+//! The idle-session reaper and its log.
+
+use crate::commands::expand_tilde;
+use crate::panes::{notify, run_tmux_on, send_to_pane};
+use crate::scan_ai_processes;
+use crate::Result;
+use rpai::support::{config_dir, ensure_config_dir, format_duration, signal_pid, unix_now};
+use rpai::{AiSession, Config, ReaperConfig, SessionState};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReapAction {
+    /// Only warn
+    Warn,
+    /// Type the agent's exit command into its pane
+    Exit,
+    /// Send the configured signal
+    Signal,
+}
+
+impl ReapAction {
+    pub(crate) fn parse(s: &str) -> Option<ReapAction> {
+        match s.to_lowercase().as_str() {
+            "warn" => Some(ReapAction::Warn),
+            "exit" => Some(ReapAction::Exit),
+            "signal" => Some(ReapAction::Signal),
+            _ => None,
+        }
+    }
+}
+
+/// Command each agent's TUI accepts to exit gracefully.
+const EXIT_COMMANDS: &[(&str, &str)] = &[
+    ("claude", "/exit"),
+    ("codex", "/quit"),
+    ("gemini", "/quit"),
+    ("opencode", "/exit"),
+];
+
+/// Minimum interval between reaper passes in the TUI loop.
+pub(crate) const REAP_INTERVAL_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ReapRecord {
+    warned_at: i64,
+    #[serde(default)]
+    acted_at: Option<i64>,
+}
+
+fn load_reap_records() -> HashMap<String, ReapRecord> {
+    fs::read_to_string(config_dir().join("reaper.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_reap_records(records: &HashMap<String, ReapRecord>) {
+    if let (Ok(dir), Ok(content)) = (ensure_config_dir(), serde_json::to_string(records)) {
+        let _ = fs::write(dir.join("reaper.json"), content);
+    }
+}
+
+/// Format a unix time as "YYYY-MM-DD HH:MM:SS" UTC.
+fn format_timestamp(unix: i64) -> String {
+    let (days, secs) = (unix.div_euclid(86400), unix.rem_euclid(86400));
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+fn log_reaper(messages: &[String]) {
+    let Ok(dir) = ensure_config_dir() else {
+        return;
+    };
+    let Ok(mut file) = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join("reaper.log"))
+    else {
+        return;
+    };
+    use std::io::Write;
+    let now = format_timestamp(unix_now());
+    for msg in messages {
+        let _ = writeln!(file, "{} {}", now, msg);
+    }
+}
+
+/// `#word` tags in a session name, without the `#`.
+fn session_tags(name: &str) -> Vec<&str> {
+    name.split_whitespace()
+        .filter_map(|w| w.strip_prefix('#'))
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+fn exempt_reason(session: &AiSession, config: &ReaperConfig) -> Option<String> {
+    if let Some(name) = &session.name {
+        let bare: Vec<&str> = name
+            .split_whitespace()
+            .filter(|w| !w.starts_with('#'))
+            .collect();
+        let bare = bare.join(" ");
+        if let Some(n) = config
+            .exempt_names
+            .iter()
+            .find(|n| n.eq_ignore_ascii_case(&bare))
+        {
+            return Some(format!("name {}", n));
+        }
+        let tags = session_tags(name);
+        if let Some(t) = config.exempt_tags.iter().find(|t| {
+            tags.iter()
+                .any(|tag| tag.eq_ignore_ascii_case(t.trim_start_matches('#')))
+        }) {
+            return Some(format!("tag #{}", t.trim_start_matches('#')));
+        }
+    }
+    config
+        .exempt_dirs
+        .iter()
+        .find(|d| {
+            let dir = expand_tilde(d);
+            let dir = dir.trim_end_matches('/');
+            session.working_dir == dir || session.working_dir.starts_with(&format!("{}/", dir))
+        })
+        .map(|d| format!("directory {}", d))
+}
+
+/// Seconds a waiting session has been idle: since it entered Waiting or its
+/// window last saw activity, whichever is later. None for running sessions.
+fn idle_seconds(session: &AiSession, now: i64) -> Option<i64> {
+    if session.state != SessionState::Waiting {
+        return None;
+    }
+    let activity = session
+        .tmux_pane()
+        .and_then(|p| {
+            run_tmux_on(
+                session.mux_socket.as_deref(),
+                &["display-message", "-p", "-t", p, "#{window_activity}"],
+            )
+            .ok()
+        })
+        .and_then(|t| t.parse::<i64>().ok())
+        .unwrap_or(0);
+    Some(now - session.state_since.max(activity))
+}
+
+/// Stop a reaped session gracefully when possible, returning what was done.
+/// The exit command is only typed into tmux panes; agents in zellij or screen,
+/// or without a known exit command, get the configured signal instead, and
+/// the message, which goes to reaper.log, says why.
+fn stop_idle_session(
+    session: &AiSession,
+    action: ReapAction,
+    config: &ReaperConfig,
+) -> std::result::Result<String, String> {
+    let exit_command = EXIT_COMMANDS
+        .iter()
+        .find(|(agent, _)| *agent == session.agent_type)
+        .map(|(_, cmd)| *cmd);
+    match (action, session.tmux_pane(), exit_command) {
+        (ReapAction::Exit, Some(_), Some(cmd)) => {
+            send_to_pane(session, cmd)?;
+            Ok(format!("sent {}", cmd))
+        }
+        (action, pane, exit_command) => {
+            signal_pid(session.pid, &config.signal).map_err(|e| e.to_string())?;
+            let sent = format!("sent SIG{}", config.signal.to_uppercase());
+            Ok(match (action, pane, exit_command) {
+                (ReapAction::Exit, _, None) => {
+                    format!("{} (no exit command for {})", sent, session.agent_type)
+                }
+                (ReapAction::Exit, None, _) => format!("{} (exit needs a tmux pane)", sent),
+                _ => sent,
+            })
+        }
+    }
+}
+
+/// One reaper pass: warn about sessions idle past `idle_hours`, and once
+/// `grace_minutes` have passed since the warning, apply the configured action.
+/// Returns a message per step taken; in dry-run mode nothing is done or recorded.
+pub(crate) fn reap_idle_sessions(
+    sessions: &[AiSession],
+    config: &ReaperConfig,
+    dry_run: bool,
+) -> std::result::Result<Vec<String>, String> {
+    let action = ReapAction::parse(&config.action).ok_or_else(|| {
+        format!(
+            "Unknown reaper action '{}'. Available: warn, exit, signal",
+            config.action
+        )
+    })?;
+    let now = unix_now();
+    let threshold = (config.idle_hours * 3600.0) as i64;
+    let grace = (config.grace_minutes * 60.0) as i64;
+    let mut records = load_reap_records();
+    let mut messages = Vec::new();
+    let prefix = if dry_run { "[dry-run] would " } else { "" };
+
+    for session in sessions {
+        let key = session.pid.to_string();
+        let idle = match idle_seconds(session, now) {
+            Some(idle) if idle >= threshold => idle,
+            // Active again: start over if it goes idle later
+            _ => {
+                records.remove(&key);
+                continue;
+            }
+        };
+        let label = format!(
+            "{} {} (PID: {}, {}) idle {}",
+            session.agent_type,
+            session.id,
+            session.pid,
+            session.working_dir,
+            format_duration(idle)
+        );
+        if let Some(reason) = exempt_reason(session, config) {
+            if dry_run {
+                messages.push(format!("[dry-run] skip {}: exempt by {}", label, reason));
+            }
+            continue;
+        }
+
+        match records.get(&key) {
+            None => {
+                messages.push(format!("{}warn {}", prefix, label));
+                if action != ReapAction::Warn && dry_run {
+                    messages.push(format!(
+                        "{}{} it {} later",
+                        prefix,
+                        config.action,
+                        format_duration(grace)
+                    ));
+                }
+                if !dry_run {
+                    notify("rpai", &format!("Idle agent: {}", label));
+                    records.insert(
+                        key,
+                        ReapRecord {
+                            warned_at: now,
+                            acted_at: None,
+                        },
+                    );
+                }
+            }
+            Some(record)
+                if action != ReapAction::Warn
+                    && record.acted_at.is_none()
+                    && now - record.warned_at >= grace =>
+            {
+                if dry_run {
+                    messages.push(format!("{}{} {}", prefix, config.action, label));
+                    continue;
+                }
+                match stop_idle_session(session, action, config) {
+                    Ok(what) => messages.push(format!("{} {}: {}", config.action, label, what)),
+                    Err(e) => {
+                        messages.push(format!("failed to {} {}: {}", config.action, label, e))
+                    }
+                }
+                if let Some(record) = records.get_mut(&key) {
+                    record.acted_at = Some(now);
+                }
+            }
+            Some(_) => {}
+        }
+    }
+
+    if !dry_run {
+        let live: Vec<String> = sessions.iter().map(|s| s.pid.to_string()).collect();
+        records.retain(|pid, _| live.contains(pid));
+        save_reap_records(&records);
+        log_reaper(&messages);
+    }
+    Ok(messages)
+}
+
+pub(crate) fn reap_command(args: &[String]) -> Result<()> {
+    let dry_run = args.iter().any(|a| a == "--dry-run");
+    if let Some(arg) = args.iter().find(|a| *a != "--dry-run") {
+        return Err(format!("Unknown option: {}\nUsage: rpai reap [--dry-run]", arg).into());
+    }
+
+    let config = Config::load();
+    if !config.reaper.enabled && !dry_run {
+        println!(
+            "The reaper is disabled. Set reaper.enabled in config.json, or preview with --dry-run"
+        );
+        return Ok(());
+    }
+
+    let sessions = scan_ai_processes()?;
+    let messages = reap_idle_sessions(&sessions, &config.reaper, dry_run || config.reaper.dry_run)?;
+    if messages.is_empty() {
+        println!("No idle sessions to reap");
+    }
+    for msg in messages {
+        println!("{}", msg);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_are_utc_dates() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00:00");
+        assert_eq!(format_timestamp(1_792_321_921), "2026-10-18 11:12:01");
+        assert_eq!(format_timestamp(-1), "1969-12-31 23:59:59");
+    }
+
+    #[test]
+    fn reaper_exemptions_match_names_tags_and_directories() {
+        let mut config = ReaperConfig::default();
+        config.exempt_names = vec!["Review".to_string()];
+        config.exempt_tags = vec!["#keep".to_string()];
+        config.exempt_dirs = vec!["/work/src/".to_string()];
+
+        let mut session = AiSession::new(1, "claude", "/tmp".to_string());
+        assert_eq!(exempt_reason(&session, &config), None);
+        session.name = Some("review #wip".to_string());
+        assert_eq!(
+            exempt_reason(&session, &config).as_deref(),
+            Some("name Review")
+        );
+        session.name = Some("nightly #KEEP".to_string());
+        assert_eq!(
+            exempt_reason(&session, &config).as_deref(),
+            Some("tag #keep")
+        );
+
+        session.name = None;
+        session.working_dir = "/work/src/api".to_string();
+        assert_eq!(
+            exempt_reason(&session, &config).as_deref(),
+            Some("directory /work/src/")
+        );
+        session.working_dir = "/work/src".to_string();
+        assert!(exempt_reason(&session, &config).is_some());
+        session.working_dir = "/work/srcs".to_string();
+        assert_eq!(exempt_reason(&session, &config), None);
+    }
+}
@@ -0,0 +1,274 @@
+//! Finding agent sessions.
+
+use crate::backend::{
+    all_multiplexer_panes, default_multiplexers, find_pane_for_pid, Multiplexer, Pane,
+    ProcessSource, SystemProcesses,
+};
+use crate::config::Config;
+use crate::error::Result;
+use crate::process::{
+    child_index, descendants, get_session_state_and_cpu, session_activity, session_details,
+    session_memory, session_ports, CpuSampler, ProcessRow, SessionDetails,
+};
+use crate::session::{load_session_names, record_state_times, session_id, AiSession};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Finds AI agent sessions running on this machine.
+///
+/// ```no_run
+/// use rpai::{Config, Scanner};
+///
+/// let scanner = Scanner::new(Config::load()).with_saved_state();
+/// for session in scanner.scan()? {
+///     println!("{} {} {:?}", session.pid, session.agent_type, session.pane_id);
+/// }
+/// # Ok::<(), rpai::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct Scanner {
+    config: Config,
+    processes: Arc<dyn ProcessSource>,
+    multiplexers: Vec<Arc<dyn Multiplexer>>,
+    /// Whether scans read names.json and write states.json
+    saved_state: bool,
+    /// What the last scan read, shared between clones
+    last: Arc<Mutex<Option<Snapshot>>>,
+    cpu: Arc<Mutex<CpuSampler>>,
+}
+
+/// The process table and panes one scan read.
+struct Snapshot {
+    rows: HashMap<u32, ProcessRow>,
+    panes: HashMap<u32, Pane>,
+}
+
+impl fmt::Debug for Scanner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scanner")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Scanner {
+    /// A scanner using `config` for state detection (`idle_threshold`),
+    /// reading the system's processes, the user's tmux servers (including
+    /// `tmux_sockets`) and zellij and screen sessions.
+    pub fn new(config: Config) -> Self {
+        let multiplexers = default_multiplexers(&config.tmux_sockets);
+        Self {
+            config,
+            processes: Arc::new(SystemProcesses),
+            multiplexers,
+            saved_state: false,
+            last: Arc::default(),
+            cpu: Arc::default(),
+        }
+    }
+
+    /// Read processes from `processes` instead of the system.
+    pub fn with_process_source(mut self, processes: impl ProcessSource + 'static) -> Self {
+        self.processes = Arc::new(processes);
+        self
+    }
+
+    /// Map agents to panes of `multiplexer` instead of tmux, zellij and screen.
+    pub fn with_multiplexer(mut self, multiplexer: impl Multiplexer + 'static) -> Self {
+        self.multiplexers = vec![Arc::new(multiplexer)];
+        self
+    }
+
+    /// Share state with the `rpai` binary through `~/.config/rpai`: apply
+    /// names given with `rpai rename`, and record state changes in
+    /// `states.json`, which is how [`AiSession::state_since`] survives across
+    /// scans and processes. Without this, scanning never touches the config
+    /// directory: sessions have no name and `state_since` is 0.
+    pub fn with_saved_state(mut self) -> Self {
+        self.saved_state = true;
+        self
+    }
+
+    /// The configuration this scanner uses.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Scan for agent processes, ordered by PID.
+    ///
+    /// Agents started by another agent are folded into their parent. CPU use is
+    /// measured across this scanner's (and its clones') scans, over windows of
+    /// at least a second; until the first window ends it is `ps`'s average
+    /// since each process started. Names and
+    /// `state_since` are only filled in [`Scanner::with_saved_state`].
+    pub fn scan(&self) -> Result<Vec<AiSession>> {
+        let mut snapshot = self.read()?;
+        let mut cpu = self.cpu.lock().unwrap_or_else(|e| e.into_inner());
+        cpu.retain(&snapshot.rows);
+        let mut sessions = detect_sessions(&self.config, &*self.processes, &mut snapshot, &mut cpu);
+        *self.last.lock().unwrap_or_else(|e| e.into_inner()) = Some(snapshot);
+
+        if self.saved_state {
+            let names = load_session_names();
+            for session in sessions.iter_mut() {
+                session.name = names.get(&session.pid.to_string()).cloned();
+            }
+            record_state_times(&mut sessions);
+        }
+        Ok(sessions)
+    }
+
+    /// The process tree and pane of the session whose agent is `pid`, as of
+    /// the last scan, so showing them costs no extra `ps` or multiplexer
+    /// calls. Reads them afresh when nothing has been scanned yet. PSS is
+    /// read now, for this session only.
+    pub fn details(&self, pid: u32) -> Result<SessionDetails> {
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        if last.is_none() {
+            *last = Some(self.read()?);
+        }
+        let snapshot = last.as_ref().expect("snapshot was just read");
+        Ok(session_details(
+            pid,
+            &snapshot.rows,
+            &snapshot.panes,
+            &*self.processes,
+        ))
+    }
+
+    fn read(&self) -> Result<Snapshot> {
+        let rows = self.processes.processes()?;
+        let panes = all_multiplexer_panes(&self.multiplexers, &rows);
+        Ok(Snapshot { rows, panes })
+    }
+}
+
+/// Whether a process is an agent, judged by its executable name and argv[0].
+/// Returns the agent type.
+fn agent_type(row: &ProcessRow, agent_pattern: &Regex) -> Option<&'static str> {
+    let comm_lower = row.comm.to_lowercase();
+    let cmd_lower = row
+        .command
+        .split_whitespace()
+        .next()
+        .unwrap_or("")
+        .to_lowercase();
+
+    // Filter out system services
+    if comm_lower.contains("cursoruiviewservice") || cmd_lower.contains("cursoruiviewservice") {
+        return None;
+    }
+    if !agent_pattern.is_match(&comm_lower) && !agent_pattern.is_match(&cmd_lower) {
+        return None;
+    }
+
+    let agent_type = if cmd_lower.contains("opencode") {
+        "opencode"
+    } else if cmd_lower.contains("claude") {
+        "claude"
+    } else if cmd_lower.contains("codex") {
+        "codex"
+    } else if cmd_lower.contains("cursor") {
+        "cursor"
+    } else if cmd_lower.contains("gemini") {
+        "gemini"
+    } else if comm_lower.contains("opencode") {
+        "opencode"
+    } else if comm_lower.contains("claude") {
+        "claude"
+    } else if comm_lower.contains("codex") {
+        "codex"
+    } else if comm_lower.contains("cursor") {
+        "cursor"
+    } else if comm_lower.contains("gemini") {
+        "gemini"
+    } else {
+        "unknown"
+    };
+    Some(agent_type)
+}
+
+fn detect_sessions(
+    config: &Config,
+    processes: &dyn ProcessSource,
+    snapshot: &mut Snapshot,
+    cpu: &mut CpuSampler,
+) -> Vec<AiSession> {
+    let children = child_index(&snapshot.rows);
+
+    let agent_pattern =
+        Regex::new(r"(?i)(opencode|claude|codex|cursor|gemini)").expect("valid agent pattern");
+
+    let matched: Vec<(u32, u32, &'static str)> = snapshot
+        .rows
+        .values()
+        .filter_map(|row| agent_type(row, &agent_pattern).map(|agent| (row.pid, row.ppid, agent)))
+        .collect();
+
+    // Filter out subprocesses - only keep processes whose parent is not also an AI agent
+    let matched_pid_set: HashSet<u32> = matched.iter().map(|&(pid, _, _)| pid).collect();
+    let agents: Vec<(u32, &'static str)> = matched
+        .into_iter()
+        .filter(|(_, ppid, _)| !matched_pid_set.contains(ppid))
+        .map(|(pid, _, agent_type)| (pid, agent_type))
+        .collect();
+
+    // Measure CPU use for the agents and their descendants, so the state, the
+    // activity and the detail view all see the same figures. Trees overlap when
+    // an agent runs another through a shell; each process is sampled once.
+    let mut tree_pids = HashSet::new();
+    for &(pid, _) in &agents {
+        tree_pids.insert(pid);
+        tree_pids.extend(
+            descendants(pid, &snapshot.rows, &children)
+                .iter()
+                .map(|n| n.row.pid),
+        );
+    }
+    for &pid in &tree_pids {
+        let cpu_time = processes.cpu_time(pid);
+        if let Some(row) = snapshot.rows.get_mut(&pid) {
+            row.cpu_percent = cpu.percent(row, cpu_time);
+        }
+    }
+    // One lookup for every agent: it reads the whole socket table either way
+    let tree_pids: Vec<u32> = tree_pids.into_iter().collect();
+    let listening = processes.listening_ports(&tree_pids);
+
+    let Snapshot { rows, panes } = &*snapshot;
+    let mut sessions = Vec::new();
+    for (pid, agent_type) in agents {
+        let row = &rows[&pid];
+        let working_dir = processes.cwd(pid).unwrap_or_else(|| "unknown".to_string());
+        let tree = descendants(pid, rows, &children);
+        let (memory_mb, lsp_memory_mb) = session_memory(row, &tree);
+        let (state, cpu_percent) = get_session_state_and_cpu(row, &tree, config.idle_threshold);
+
+        let mut session = AiSession {
+            id: session_id(pid, &row.started),
+            uptime_seconds: row.elapsed_seconds,
+            memory_mb,
+            lsp_memory_mb,
+            cpu_percent,
+            state,
+            activity: session_activity(state, &tree),
+            ports: session_ports(pid, &tree, rows, &listening),
+            ..AiSession::new(pid, agent_type, working_dir)
+        };
+        if let Some((_chain, pane)) = find_pane_for_pid(pid, rows, panes) {
+            session.multiplexer = Some(pane.multiplexer);
+            session.pane_id = Some(pane.pane_id);
+            session.mux_session = Some(pane.session);
+            session.mux_window = pane.window;
+            session.pane_width = pane.width;
+            session.pane_height = pane.height;
+            session.mux_socket = pane.socket;
+        }
+        sessions.push(session);
+    }
+
+    sessions.sort_by_key(|s| s.pid);
+    sessions
+}
@@ -0,0 +1,85 @@
+//! GNU screen integration: mapping processes to windows through the `STY`
+//! and `WINDOW` variables screen gives window processes, and selecting windows.
+
+use crate::backend::Pane;
+use crate::error::{run_command, Result};
+use crate::process::{server_children_env, ProcessRow};
+use crate::session::MultiplexerKind;
+use std::collections::HashMap;
+use std::env;
+
+/// Every window of every running screen session, keyed by the PID of the
+/// process running in it: the children of screen servers, identified by the
+/// `STY` and `WINDOW` variables in their environment. The pane id is
+/// `<sty>:<window>`. `processes` is the process table to look for servers in.
+/// Empty where there is no `/proc`.
+pub(crate) fn get_screen_windows(processes: &HashMap<u32, ProcessRow>) -> HashMap<u32, Pane> {
+    server_children_env("screen", &["STY", "WINDOW"], processes)
+        .into_iter()
+        .filter_map(|(pid, mut env)| {
+            let sty = env.remove("STY")?;
+            let window = env.remove("WINDOW")?;
+            Some((
+                pid,
+                Pane {
+                    multiplexer: MultiplexerKind::Screen,
+                    pane_id: format!("{}:{}", sty, window),
+                    session: sty,
+                    window: Some(window),
+                    width: None,
+                    height: None,
+                    socket: None,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// The screen session this process runs in, from `$STY`.
+pub(crate) fn current_sty() -> Option<String> {
+    env::var("STY").ok()
+}
+
+/// The screen window this process runs in, as "<sty>:<window>".
+pub(crate) fn current_screen_window() -> Option<String> {
+    let sty = current_sty()?;
+    let window = env::var("WINDOW").ok()?;
+    Some(format!("{}:{}", sty, window))
+}
+
+/// Whether a terminal is attached to the screen session `sty`, from `screen -ls`.
+pub(crate) fn is_attached(sty: &str) -> bool {
+    // `screen -ls` exits with status 1 even when it lists sessions
+    let output = std::process::Command::new("screen").arg("-ls").output();
+    output.is_ok_and(|o| lists_attached(&String::from_utf8_lossy(&o.stdout), sty))
+}
+
+/// Whether the `screen -ls` output `listing` shows `sty` as attached. The
+/// session's name is the first field of its line, matched exactly so `4.a`
+/// does not match `14.a` or `4.ab`.
+fn lists_attached(listing: &str, sty: &str) -> bool {
+    listing
+        .lines()
+        .any(|line| line.split_whitespace().next() == Some(sty) && line.contains("(Attached)"))
+}
+
+/// Make `window` the current window of the screen session `sty`.
+pub(crate) fn select_window(sty: &str, window: &str) -> Result<()> {
+    run_command("screen", &["-S", sty, "-X", "select", window]).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attached_sessions_match_their_whole_name() {
+        let listing = "There are screens on:\n\
+            \t14.pts-1.box\t(10/18/2026 09:12:01 AM)\t(Attached)\n\
+            \t4.pts-1.box\t(10/18/2026 09:10:00 AM)\t(Detached)\n\
+            2 Sockets in /run/screen/S-me.\n";
+        assert!(lists_attached(listing, "14.pts-1.box"));
+        assert!(!lists_attached(listing, "4.pts-1.box"));
+        assert!(!lists_attached(listing, "4.pts-1.bo"));
+    }
+}
@@ -0,0 +1,348 @@
+//! Session data: what rpai knows about one running agent.
+
+use crate::config::{config_dir, ensure_config_dir};
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+/// Whether an agent is working or waiting for the user, judged by the CPU
+/// usage of its process tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum SessionState {
+    /// Using more CPU than the configured idle threshold
+    Running,
+    /// Idle, usually waiting for input
+    Waiting,
+}
+
+impl SessionState {
+    /// Lowercase name, e.g. "running".
+    pub fn name(&self) -> &'static str {
+        match self {
+            SessionState::Running => "running",
+            SessionState::Waiting => "waiting",
+        }
+    }
+
+    /// Symbol shown next to the session, `▶`/`⏸` or `>>`/`||` in ASCII mode.
+    pub fn symbol(&self, ascii: bool) -> &str {
+        match self {
+            SessionState::Running => {
+                if ascii {
+                    ">>"
+                } else {
+                    "▶"
+                }
+            }
+            SessionState::Waiting => {
+                if ascii {
+                    "||"
+                } else {
+                    "⏸"
+                }
+            }
+        }
+    }
+}
+
+/// A terminal multiplexer agents run in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum MultiplexerKind {
+    /// tmux, including servers on other sockets
+    Tmux,
+    /// zellij
+    Zellij,
+    /// GNU screen, whose windows stand in for panes
+    Screen,
+}
+
+impl MultiplexerKind {
+    /// Lowercase name, e.g. "tmux".
+    pub fn name(&self) -> &'static str {
+        match self {
+            MultiplexerKind::Tmux => "tmux",
+            MultiplexerKind::Zellij => "zellij",
+            MultiplexerKind::Screen => "screen",
+        }
+    }
+}
+
+/// A detected AI agent process and where it runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct AiSession {
+    /// PID of the top-level agent process
+    pub pid: u32,
+    /// Short ID that stays the same for the life of the agent process, derived
+    /// from its PID and start time
+    #[serde(default)]
+    pub id: String,
+    /// Agent kind: claude, codex, opencode, cursor, gemini or unknown
+    pub agent_type: String,
+    /// Working directory of the agent
+    pub working_dir: String,
+    /// Display name given with `rename`
+    pub name: Option<String>,
+    /// Multiplexer whose pane the agent runs in, None outside any
+    #[serde(default)]
+    pub multiplexer: Option<MultiplexerKind>,
+    /// Pane id, e.g. "%3" in tmux, "terminal_2" in zellij or `<sty>:<window>` in screen
+    pub pane_id: Option<String>,
+    /// Multiplexer session containing the pane (`$STY` in screen)
+    #[serde(default)]
+    pub mux_session: Option<String>,
+    /// Window (tmux, screen) or tab (zellij) containing the pane, when known
+    #[serde(default)]
+    pub mux_window: Option<String>,
+    /// Pane width in cells, when known
+    pub pane_width: Option<u32>,
+    /// Pane height in cells, when known
+    pub pane_height: Option<u32>,
+    /// Socket of the multiplexer server the pane belongs to, when not the default one
+    #[serde(default)]
+    pub mux_socket: Option<String>,
+    /// Seconds since the agent started
+    pub uptime_seconds: i64,
+    /// Resident memory of the agent and its non-LSP descendants
+    pub memory_mb: u64,
+    /// Resident memory of LSP servers in the tree, kept out of `memory_mb`
+    #[serde(default)]
+    pub lsp_memory_mb: u64,
+    /// CPU usage of the agent and its non-LSP descendants
+    pub cpu_percent: f64,
+    /// Running or waiting
+    pub state: SessionState,
+    /// Unix time (seconds) at which the session entered its current state
+    pub state_since: i64,
+    /// What a running agent is doing, e.g. "running: cargo test (2m)" or "thinking"
+    #[serde(default)]
+    pub activity: Option<String>,
+    /// TCP ports listened on by the agent or its descendants
+    #[serde(default)]
+    pub ports: Vec<ListeningPort>,
+}
+
+impl AiSession {
+    /// A waiting session outside tmux with no usage figures, for filling in
+    /// field by field. Its `id` is derived from the PID alone.
+    pub fn new(pid: u32, agent_type: impl Into<String>, working_dir: impl Into<String>) -> Self {
+        Self {
+            pid,
+            id: session_id(pid, ""),
+            agent_type: agent_type.into(),
+            working_dir: working_dir.into(),
+            name: None,
+            multiplexer: None,
+            pane_id: None,
+            mux_session: None,
+            mux_window: None,
+            pane_width: None,
+            pane_height: None,
+            mux_socket: None,
+            uptime_seconds: 0,
+            memory_mb: 0,
+            lsp_memory_mb: 0,
+            cpu_percent: 0.0,
+            state: SessionState::Waiting,
+            state_since: 0,
+            activity: None,
+            ports: Vec::new(),
+        }
+    }
+
+    /// The pane id, when the agent runs in a tmux pane.
+    pub fn tmux_pane(&self) -> Option<&str> {
+        self.pane_id
+            .as_deref()
+            .filter(|_| self.multiplexer == Some(MultiplexerKind::Tmux))
+    }
+
+    /// Name of the tmux server the pane belongs to when it is not the default
+    /// one, i.e. its socket's file name.
+    pub fn mux_server(&self) -> Option<&str> {
+        let socket = self.mux_socket.as_deref()?;
+        let name = socket.rsplit('/').next().unwrap_or(socket);
+        (name != "default").then_some(name)
+    }
+
+    /// Where the agent runs, e.g. "work:1 %3" for a tmux pane ("work:1 %3 @other"
+    /// on a non-default server), "zellij dev terminal_2" or `<sty>:<window>` for
+    /// a screen window; None outside any multiplexer.
+    pub fn location(&self) -> Option<String> {
+        let kind = self.multiplexer?;
+        let session = self.mux_session.as_deref().unwrap_or("-");
+        let pane = self.pane_id.as_deref().unwrap_or("-");
+        let place = match &self.mux_window {
+            Some(window) => format!("{}:{} {}", session, window, pane),
+            None => format!("{} {}", session, pane),
+        };
+        Some(match (kind, self.mux_server()) {
+            (MultiplexerKind::Tmux, Some(server)) => format!("{} @{}", place, server),
+            (MultiplexerKind::Tmux, None) => place,
+            (MultiplexerKind::Screen, _) => pane.to_string(),
+            (kind, _) => format!("{} {}", kind.name(), place),
+        })
+    }
+}
+
+/// Short ID of the agent process `pid` started at `started` (as reported by
+/// `ps -o lstart`): four characters, a letter followed by letters or digits,
+/// so it never reads as a list position. A reused PID gets a different ID.
+pub(crate) fn session_id(pid: u32, started: &str) -> String {
+    const LETTERS: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+    const ALPHANUMERIC: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+    // FNV-1a, which unlike std's hasher gives the same result in every build
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in format!("{}@{}", pid, started).bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+
+    let mut id = String::with_capacity(4);
+    id.push(LETTERS[(hash % 26) as usize] as char);
+    hash /= 26;
+    for _ in 0..3 {
+        id.push(ALPHANUMERIC[(hash % 36) as usize] as char);
+        hash /= 36;
+    }
+    id
+}
+
+/// Whether `target` has the shape of an [`AiSession::id`].
+pub fn is_session_id(target: &str) -> bool {
+    target.len() == 4
+        && target.starts_with(|c: char| c.is_ascii_lowercase())
+        && target
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+}
+
+/// A TCP socket in LISTEN state owned by a process in a session's tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ListeningPort {
+    /// Port number
+    pub port: u16,
+    /// PID of the process owning the socket
+    pub pid: u32,
+    /// Short server name derived from the owning process's command line
+    pub name: String,
+}
+
+impl ListeningPort {
+    /// A port listened on by `pid`, a server called `name`.
+    pub fn new(port: u16, pid: u32, name: &str) -> Self {
+        Self {
+            port,
+            pid,
+            name: name.to_string(),
+        }
+    }
+
+    /// The port and server name, e.g. ":3000 (vite)".
+    pub fn label(&self) -> String {
+        format!(":{} ({})", self.port, self.name)
+    }
+
+    /// The URL the server most likely answers on.
+    pub fn url(&self) -> String {
+        format!("http://localhost:{}", self.port)
+    }
+}
+
+/// Display names given to sessions with `rename`, keyed by PID.
+pub(crate) fn load_session_names() -> HashMap<String, String> {
+    fs::read_to_string(config_dir().join("names.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Set or clear a session's display name. Names of processes that are no
+/// longer running are dropped so PIDs reused later don't inherit them.
+pub fn save_session_name(pid: u32, name: Option<&str>, live_pids: &[u32]) -> Result<()> {
+    let mut names = load_session_names();
+    names.retain(|k, _| k.parse().is_ok_and(|p: u32| live_pids.contains(&p)));
+    match name {
+        Some(name) => names.insert(pid.to_string(), name.to_string()),
+        None => names.remove(&pid.to_string()),
+    };
+    let dir = ensure_config_dir()?;
+    fs::write(
+        dir.join("names.json"),
+        serde_json::to_string_pretty(&names)?,
+    )?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StateRecord {
+    state: SessionState,
+    since: i64,
+}
+
+/// Current unix time in seconds.
+pub fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Fill in `state_since` from the state log in `~/.config/rpai/states.json`,
+/// recording the current time for sessions whose state changed since the last
+/// scan. The log is shared by every rpai invocation, so transitions are timed
+/// to the resolution of whichever scan saw them first.
+pub(crate) fn record_state_times(sessions: &mut [AiSession]) {
+    let path = config_dir().join("states.json");
+    let previous: HashMap<String, StateRecord> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+
+    let now = unix_now();
+    let mut records: HashMap<String, StateRecord> = HashMap::new();
+    for session in sessions.iter_mut() {
+        let key = session.pid.to_string();
+        let since = match previous.get(&key) {
+            Some(record) if record.state == session.state => record.since,
+            _ => now,
+        };
+        session.state_since = since;
+        records.insert(
+            key,
+            StateRecord {
+                state: session.state,
+                since,
+            },
+        );
+    }
+
+    let changed = records.len() != previous.len()
+        || records
+            .iter()
+            .any(|(k, r)| previous.get(k).map(|p| p.since) != Some(r.since));
+    if changed {
+        if let (Ok(dir), Ok(content)) = (ensure_config_dir(), serde_json::to_string(&records)) {
+            let _ = fs::write(dir.join("states.json"), content);
+        }
+    }
+}
+
+/// Short human duration: "42s", "5m" or "2h 13m".
+pub fn format_duration(seconds: i64) -> String {
+    let minutes = seconds / 60;
+    let hours = minutes / 60;
+
+    if hours > 0 {
+        format!("{}h {}m", hours, minutes % 60)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", seconds)
+    }
+}
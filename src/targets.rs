@@ -0,0 +1,412 @@
+//! Session targets and selectors shared by the CLI and command mode.
+
+use rpai::support::{is_session_id, same_server};
+use rpai::{AiSession, SessionState};
+use std::env;
+use std::io::stdout;
+
+/// One `key:value` term of a session selector such as `agent:claude,state:waiting`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SelectorTerm {
+    /// Agent type, ignoring case
+    Agent(String),
+    /// Working directory or one of its parents when absolute (`~` expanded),
+    /// otherwise a substring of it
+    Dir(String),
+    State(SessionState),
+    /// Exact pane id, e.g. `%12`, optionally with the tmux server (see [`in_pane`])
+    Pane(String),
+    /// Substring of the display name or multiplexer session name
+    Name(String),
+    Pid(u32),
+    /// Exact session ID
+    Id(String),
+}
+
+const SELECTOR_KEYS: &[&str] = &["agent", "dir", "state", "pane", "name", "pid", "id"];
+
+impl SelectorTerm {
+    pub(crate) fn parse(term: &str) -> std::result::Result<SelectorTerm, String> {
+        let (key, value) = term
+            .split_once(':')
+            .ok_or_else(|| format!("Selector term '{}' is not key:value", term))?;
+        if value.is_empty() {
+            return Err(format!("Selector term '{}' has no value", term));
+        }
+        match key {
+            "agent" => Ok(SelectorTerm::Agent(value.to_string())),
+            "dir" => Ok(SelectorTerm::Dir(match value.strip_prefix('~') {
+                Some(rest) => format!("{}{}", env::var("HOME").unwrap_or_default(), rest),
+                None => value.to_string(),
+            })),
+            // rpai can't tell an input prompt from an idle agent, so
+            // needs-input selects waiting sessions
+            "state" => match value {
+                "waiting" | "needs-input" => Ok(SelectorTerm::State(SessionState::Waiting)),
+                "running" => Ok(SelectorTerm::State(SessionState::Running)),
+                _ => Err(format!(
+                    "Invalid state: {}. Use 'waiting', 'needs-input' or 'running'",
+                    value
+                )),
+            },
+            "pane" => Ok(SelectorTerm::Pane(value.to_string())),
+            "name" => Ok(SelectorTerm::Name(value.to_string())),
+            "pid" => value
+                .parse()
+                .map(SelectorTerm::Pid)
+                .map_err(|_| format!("Invalid PID: {}", value)),
+            "id" => Ok(SelectorTerm::Id(value.to_string())),
+            _ => Err(format!(
+                "Unknown selector key '{}'. Available: {}",
+                key,
+                SELECTOR_KEYS.join(", ")
+            )),
+        }
+    }
+
+    pub(crate) fn matches(&self, session: &AiSession) -> bool {
+        match self {
+            SelectorTerm::Agent(agent) => session.agent_type.eq_ignore_ascii_case(agent),
+            SelectorTerm::Dir(dir) if dir.starts_with('/') => {
+                let dir = dir.trim_end_matches('/');
+                session.working_dir == dir
+                    || session
+                        .working_dir
+                        .strip_prefix(dir)
+                        .is_some_and(|rest| rest.starts_with('/'))
+            }
+            SelectorTerm::Dir(dir) => session.working_dir.contains(dir.as_str()),
+            SelectorTerm::State(state) => session.state == *state,
+            SelectorTerm::Pane(pane) => in_pane(session, pane),
+            SelectorTerm::Name(name) => session
+                .name
+                .iter()
+                .chain(&session.mux_session)
+                .any(|n| n.contains(name.as_str())),
+            SelectorTerm::Pid(pid) => session.pid == *pid,
+            SelectorTerm::Id(id) => session.id == *id,
+        }
+    }
+}
+
+/// Whether `session` runs in `pane`: a pane id such as `%3` on the tmux server
+/// plain commands reach, or `%3@name` on the server with socket `name`. Pane ids
+/// of zellij and screen are matched as they are.
+fn in_pane(session: &AiSession, pane: &str) -> bool {
+    let Some(pane_id) = session.pane_id.as_deref() else {
+        return false;
+    };
+    let socket = session.mux_socket.as_deref();
+    match pane.split_once('@') {
+        Some((pane, server)) => {
+            pane_id == pane && socket.is_some_and(|s| s.rsplit('/').next() == Some(server))
+        }
+        None => pane_id == pane && same_server(socket, None),
+    }
+}
+
+/// Parse `target` as a selector: comma-separated `key:value` terms that a
+/// session must all match. None when `target` is a plain target, i.e. its
+/// first term does not start with a selector key (so `work:1` stays a name).
+pub(crate) fn parse_selector(
+    target: &str,
+) -> Option<std::result::Result<Vec<SelectorTerm>, String>> {
+    let first_key = target.split(',').next()?.split_once(':')?.0;
+    if !SELECTOR_KEYS.contains(&first_key) {
+        return None;
+    }
+    Some(
+        target
+            .split(',')
+            .map(|t| SelectorTerm::parse(t.trim()))
+            .collect(),
+    )
+}
+
+/// Indices of sessions matching a target: a selector (see [`parse_selector`]),
+/// a 1-based list position, a session ID, a pane id, or a (substring of a)
+/// multiplexer session name or rpai display name. IDs and pane ids only match
+/// exactly. Fails on a malformed selector.
+fn find_sessions(sessions: &[AiSession], target: &str) -> std::result::Result<Vec<usize>, String> {
+    if let Some(selector) = parse_selector(target) {
+        let terms = selector?;
+        return Ok((0..sessions.len())
+            .filter(|&i| terms.iter().all(|t| t.matches(&sessions[i])))
+            .collect());
+    }
+
+    if let Ok(id) = target.parse::<usize>() {
+        return Ok(if id >= 1 && id <= sessions.len() {
+            vec![id - 1]
+        } else {
+            Vec::new()
+        });
+    }
+
+    let names = |s: &AiSession| {
+        s.mux_session
+            .clone()
+            .into_iter()
+            .chain(s.name.clone())
+            .collect::<Vec<_>>()
+    };
+    let exact: Vec<usize> = (0..sessions.len())
+        .filter(|&i| {
+            let session = &sessions[i];
+            session.id == target
+                || in_pane(session, target)
+                || names(session).iter().any(|n| n == target)
+        })
+        .collect();
+    if !exact.is_empty() {
+        return Ok(exact);
+    }
+    Ok((0..sessions.len())
+        .filter(|&i| names(&sessions[i]).iter().any(|n| n.contains(target)))
+        .collect())
+}
+
+/// The one session a target refers to (see [`find_sessions`]), or an error
+/// saying why there is none: a stale ID or position, or an ambiguous target.
+pub(crate) fn resolve_session(
+    sessions: &[AiSession],
+    target: &str,
+) -> std::result::Result<usize, String> {
+    let matching = find_sessions(sessions, target)?;
+    single_match(sessions, target, &matching)
+}
+
+/// The only index in `matching`, the sessions `target` matched.
+fn single_match(
+    sessions: &[AiSession],
+    target: &str,
+    matching: &[usize],
+) -> std::result::Result<usize, String> {
+    match matching.len() {
+        1 => Ok(matching[0]),
+        // An ID-shaped word like "work" may well be a name
+        0 if is_session_id(target) && target.contains(|c: char| c.is_ascii_digit()) => Err(
+            format!("No session with ID {} (its agent may have exited)", target),
+        ),
+        0 if is_session_id(target) => Err(format!(
+            "No session found matching: {} (if it is a session ID, its agent may have exited)",
+            target
+        )),
+        0 => match target.parse::<usize>() {
+            Ok(position) => Err(format!(
+                "No session at position {} (there are {})",
+                position,
+                sessions.len()
+            )),
+            Err(_) => Err(format!("No session found matching: {}", target)),
+        },
+        _ => Err(format!(
+            "'{}' matches several sessions, use an ID: {}",
+            target,
+            matching
+                .iter()
+                .map(|&i| format!("{} ({})", sessions[i].id, session_label(&sessions[i])))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// "claude work:1" or "claude reviewer": the agent and its name or location.
+fn session_label(session: &AiSession) -> String {
+    match session.name.clone().or_else(|| session.location()) {
+        Some(place) => format!("{} {}", session.agent_type, place),
+        None => format!("{} PID {}", session.agent_type, session.pid),
+    }
+}
+
+/// Resolve a CLI session argument (see [`find_sessions`]). When several
+/// sessions match and stdin is a terminal, asks which one to use. None when
+/// the user cancels; an error saying why when nothing was resolved.
+pub(crate) fn resolve_cli_session<'a>(
+    sessions: &'a [AiSession],
+    id_str: &str,
+) -> std::result::Result<Option<&'a AiSession>, String> {
+    use std::io::IsTerminal;
+
+    let resolved = find_sessions(sessions, id_str).and_then(|matching| {
+        if matching.len() > 1 && std::io::stdin().is_terminal() {
+            pick_session(sessions, id_str, &matching)
+        } else {
+            single_match(sessions, id_str, &matching).map(Some)
+        }
+    });
+    match resolved {
+        Ok(idx) => Ok(idx.map(|i| &sessions[i])),
+        Err(e) => Err(format!("{}\nUse 'rpai scan' to see available sessions", e)),
+    }
+}
+
+/// Ask which of the sessions in `matching` to use. None when the user
+/// cancels with an empty answer.
+fn pick_session(
+    sessions: &[AiSession],
+    target: &str,
+    matching: &[usize],
+) -> std::result::Result<Option<usize>, String> {
+    use std::io::Write;
+
+    println!("'{}' matches {} sessions:", target, matching.len());
+    for (n, &i) in matching.iter().enumerate() {
+        let session = &sessions[i];
+        println!(
+            "  {}) {}  {:<8} {:<8} {}  {}",
+            n + 1,
+            session.id,
+            session.agent_type,
+            session.state.name(),
+            session
+                .name
+                .clone()
+                .or_else(|| session.location())
+                .unwrap_or_else(|| "-".to_string()),
+            session.working_dir
+        );
+    }
+    loop {
+        print!("Pick one [1-{}, Enter to cancel]: ", matching.len());
+        stdout().flush().map_err(|e| e.to_string())?;
+        let mut answer = String::new();
+        if std::io::stdin()
+            .read_line(&mut answer)
+            .map_err(|e| e.to_string())?
+            == 0
+        {
+            return Ok(None);
+        }
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(None);
+        }
+        match answer.parse::<usize>() {
+            Ok(n) if n >= 1 && n <= matching.len() => return Ok(Some(matching[n - 1])),
+            _ => println!("Enter a number from 1 to {}", matching.len()),
+        }
+    }
+}
+
+/// The state `rpai next` looks for: the one given with `--state` or as a
+/// `state:` term of the selector, waiting by default. Fails when the two differ.
+pub(crate) fn next_state(
+    state_flag: Option<SessionState>,
+    selector: &[SelectorTerm],
+) -> std::result::Result<SessionState, String> {
+    let selected = selector.iter().find_map(|t| match t {
+        SelectorTerm::State(state) => Some(*state),
+        _ => None,
+    });
+    match (state_flag, selected) {
+        (Some(flag), Some(selected)) if flag != selected => Err(format!(
+            "--state {} conflicts with state:{}",
+            flag.name(),
+            selected.name()
+        )),
+        (flag, selected) => Ok(flag.or(selected).unwrap_or(SessionState::Waiting)),
+    }
+}
+
+/// Pick the next session to visit: the one that has been in `state` the
+/// longest, or, when the focused pane is already one of the candidates, the
+/// one after it in that order so repeated calls cycle through them.
+pub(crate) fn pick_next_session<'a>(
+    sessions: &'a [AiSession],
+    state: SessionState,
+    agent: Option<&str>,
+    current_pane: Option<&str>,
+) -> Option<&'a AiSession> {
+    let mut candidates: Vec<&AiSession> = sessions
+        .iter()
+        .filter(|s| s.state == state && s.pane_id.is_some())
+        .filter(|s| agent.is_none_or(|a| s.agent_type.eq_ignore_ascii_case(a)))
+        .collect();
+    candidates.sort_by_key(|s| (s.state_since, s.pid));
+
+    let current = current_pane.and_then(|pane| candidates.iter().position(|s| in_pane(s, pane)));
+    match current {
+        Some(i) => candidates.get((i + 1) % candidates.len()).copied(),
+        None => candidates.first().copied(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::fixtures::fixture_sessions;
+
+    #[test]
+    fn targets_resolve_by_id_position_and_pane() {
+        let sessions = fixture_sessions();
+        assert_eq!(resolve_session(&sessions, &sessions[1].id), Ok(1));
+        assert_eq!(resolve_session(&sessions, "2"), Ok(1));
+        assert_eq!(resolve_session(&sessions, "%7"), Ok(2));
+        assert_eq!(resolve_session(&sessions, "reviewer"), Ok(2));
+    }
+
+    #[test]
+    fn stale_and_ambiguous_targets_are_errors() {
+        let sessions = fixture_sessions();
+        let stale = resolve_session(&sessions, "z9zz").unwrap_err();
+        assert!(stale.contains("No session with ID z9zz"), "{}", stale);
+        // A word shaped like an ID may be a name
+        let word = resolve_session(&sessions, "zzzz").unwrap_err();
+        assert!(word.contains("No session found matching: zzzz"), "{}", word);
+        let gone = resolve_session(&sessions, "9").unwrap_err();
+        assert!(gone.contains("No session at position 9"), "{}", gone);
+
+        // "r" is in both "work" and "review"
+        let ambiguous = resolve_session(&sessions, "r").unwrap_err();
+        assert!(ambiguous.contains(&sessions[0].id), "{}", ambiguous);
+        assert!(ambiguous.contains(&sessions[2].id), "{}", ambiguous);
+    }
+
+    #[test]
+    fn selectors_combine_terms() {
+        let sessions = fixture_sessions();
+        let find = |target: &str| find_sessions(&sessions, target);
+        assert_eq!(find("agent:claude"), Ok(vec![0]));
+        assert_eq!(find("state:waiting"), Ok(vec![1, 2]));
+        assert_eq!(find("state:waiting,dir:/work"), Ok(vec![1]));
+        assert_eq!(find("dir:/work/src"), Ok(vec![0]));
+        assert_eq!(find("dir:/work/sr"), Ok(vec![]));
+        assert_eq!(find("pane:%7,name:review"), Ok(vec![2]));
+        assert_eq!(find("pid:5150"), Ok(vec![1]));
+        assert!(find("agent:claude,color:red").is_err());
+        assert!(find("state:asleep").is_err());
+
+        // tmux-style session:window targets are not selectors
+        assert!(parse_selector("work:1").is_none());
+    }
+
+    #[test]
+    fn next_takes_its_state_from_the_flag_or_the_selector() {
+        let running = [SelectorTerm::State(SessionState::Running)];
+        assert_eq!(next_state(None, &[]), Ok(SessionState::Waiting));
+        assert_eq!(next_state(None, &running), Ok(SessionState::Running));
+        assert_eq!(
+            next_state(Some(SessionState::Running), &running),
+            Ok(SessionState::Running)
+        );
+        assert!(next_state(Some(SessionState::Waiting), &running).is_err());
+    }
+
+    #[test]
+    fn pane_ids_are_told_apart_by_server() {
+        let mut sessions = fixture_sessions();
+        let mut other = sessions[2].clone();
+        other.pid = 6000;
+        other.id = "o6k2".to_string();
+        other.mux_socket = Some("/tmp/rpai-test/other".to_string());
+        sessions.push(other);
+
+        let find = |target: &str| find_sessions(&sessions, target);
+        assert_eq!(find("pane:%7"), Ok(vec![2]));
+        assert_eq!(find("pane:%7@other"), Ok(vec![3]));
+        assert_eq!(find("%7@other"), Ok(vec![3]));
+        assert_eq!(find("pane:%7@elsewhere"), Ok(vec![]));
+    }
+}
@@ -0,0 +1,608 @@
+//! Color themes, terminal color support and the persisted theme choice.
+
+use crate::Result;
+use ratatui::{
+    buffer::Buffer,
+    style::{Color, Modifier, Style},
+};
+use rpai::support::config_dir;
+use rpai::Config;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum ThemeName {
+    Gruvbox,
+    Nord,
+    Catppuccin,
+    Dracula,
+    Tokyo,
+    Solarized,
+    Ansi,
+}
+
+impl ThemeName {
+    pub(crate) fn all() -> Vec<ThemeName> {
+        vec![
+            ThemeName::Gruvbox,
+            ThemeName::Nord,
+            ThemeName::Catppuccin,
+            ThemeName::Dracula,
+            ThemeName::Tokyo,
+            ThemeName::Solarized,
+            ThemeName::Ansi,
+        ]
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ThemeName::Gruvbox => "gruvbox",
+            ThemeName::Nord => "nord",
+            ThemeName::Catppuccin => "catppuccin",
+            ThemeName::Dracula => "dracula",
+            ThemeName::Tokyo => "tokyo",
+            ThemeName::Solarized => "solarized",
+            ThemeName::Ansi => "ansi",
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<ThemeName> {
+        match s.to_lowercase().as_str() {
+            "gruvbox" => Some(ThemeName::Gruvbox),
+            "nord" => Some(ThemeName::Nord),
+            "catppuccin" | "cat" => Some(ThemeName::Catppuccin),
+            "dracula" => Some(ThemeName::Dracula),
+            "tokyo" | "tokyonight" => Some(ThemeName::Tokyo),
+            "solarized" | "solar" => Some(ThemeName::Solarized),
+            "ansi" | "terminal" => Some(ThemeName::Ansi),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Theme {
+    pub(crate) fg: Color,
+    pub(crate) dim: Color,
+    pub(crate) accent: Color,
+    pub(crate) green: Color,
+    pub(crate) blue: Color,
+    pub(crate) aqua: Color,
+    pub(crate) orange: Color,
+    /// Background of the selected row; None highlights it with reverse video
+    selected_bg: Option<Color>,
+    pub(crate) folder_icon: Color,
+}
+
+impl Theme {
+    pub(crate) fn from_name(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Gruvbox => Theme {
+                fg: Color::Rgb(235, 219, 178),             // #ebdbb2
+                dim: Color::Rgb(146, 131, 116),            // #928374
+                accent: Color::Rgb(250, 189, 47),          // #fabd2f gold
+                green: Color::Rgb(184, 187, 38),           // #b8bb26
+                blue: Color::Rgb(131, 165, 152),           // #83a598
+                aqua: Color::Rgb(142, 192, 124),           // #8ec07c
+                orange: Color::Rgb(254, 128, 25),          // #fe8019
+                selected_bg: Some(Color::Rgb(80, 73, 69)), // #504945
+                folder_icon: Color::Rgb(250, 189, 47),
+            },
+            ThemeName::Nord => Theme {
+                fg: Color::Rgb(236, 239, 244),             // #eceff4
+                dim: Color::Rgb(76, 86, 106),              // #4c566a
+                accent: Color::Rgb(136, 192, 208),         // #88c0d0 frost
+                green: Color::Rgb(163, 190, 140),          // #a3be8c
+                blue: Color::Rgb(129, 161, 193),           // #81a1c1
+                aqua: Color::Rgb(143, 188, 187),           // #8fbcbb
+                orange: Color::Rgb(208, 135, 112),         // #d08770
+                selected_bg: Some(Color::Rgb(67, 76, 94)), // #434c5e
+                folder_icon: Color::Rgb(235, 203, 139),
+            },
+            ThemeName::Catppuccin => Theme {
+                fg: Color::Rgb(205, 214, 244),             // #cdd6f4 text
+                dim: Color::Rgb(108, 112, 134),            // #6c7086 overlay0
+                accent: Color::Rgb(245, 194, 231),         // #f5c2e7 pink
+                green: Color::Rgb(166, 227, 161),          // #a6e3a1
+                blue: Color::Rgb(137, 180, 250),           // #89b4fa
+                aqua: Color::Rgb(148, 226, 213),           // #94e2d5 teal
+                orange: Color::Rgb(250, 179, 135),         // #fab387 peach
+                selected_bg: Some(Color::Rgb(69, 71, 90)), // #45475a surface0
+                folder_icon: Color::Rgb(250, 179, 135),
+            },
+            ThemeName::Dracula => Theme {
+                fg: Color::Rgb(248, 248, 242),             // #f8f8f2
+                dim: Color::Rgb(98, 114, 164),             // #6272a4 comment
+                accent: Color::Rgb(255, 121, 198),         // #ff79c6 pink
+                green: Color::Rgb(80, 250, 123),           // #50fa7b
+                blue: Color::Rgb(139, 233, 253),           // #8be9fd cyan
+                aqua: Color::Rgb(139, 233, 253),           // #8be9fd
+                orange: Color::Rgb(255, 184, 108),         // #ffb86c
+                selected_bg: Some(Color::Rgb(68, 71, 90)), // #44475a
+                folder_icon: Color::Rgb(255, 184, 108),
+            },
+            ThemeName::Tokyo => Theme {
+                fg: Color::Rgb(192, 202, 245),             // #c0caf5
+                dim: Color::Rgb(86, 95, 137),              // #565f89
+                accent: Color::Rgb(187, 154, 247),         // #bb9af7 purple
+                green: Color::Rgb(158, 206, 106),          // #9ece6a
+                blue: Color::Rgb(125, 207, 255),           // #7dcfff
+                aqua: Color::Rgb(115, 218, 202),           // #73daca
+                orange: Color::Rgb(255, 158, 100),         // #ff9e64
+                selected_bg: Some(Color::Rgb(52, 59, 88)), // #343b58
+                folder_icon: Color::Rgb(224, 175, 104),
+            },
+            ThemeName::Solarized => Theme {
+                fg: Color::Rgb(131, 148, 150),            // #839496 base0
+                dim: Color::Rgb(88, 110, 117),            // #586e75 base01
+                accent: Color::Rgb(181, 137, 0),          // #b58900 yellow
+                green: Color::Rgb(133, 153, 0),           // #859900
+                blue: Color::Rgb(38, 139, 210),           // #268bd2
+                aqua: Color::Rgb(42, 161, 152),           // #2aa198 cyan
+                orange: Color::Rgb(203, 75, 22),          // #cb4b16
+                selected_bg: Some(Color::Rgb(7, 54, 66)), // #073642 base02
+                folder_icon: Color::Rgb(38, 139, 210),
+            },
+            // Terminal's 16 base colors, so it follows light and dark palettes alike
+            ThemeName::Ansi => Theme {
+                fg: Color::Reset,
+                dim: Color::DarkGray,
+                accent: Color::Magenta,
+                green: Color::Green,
+                blue: Color::Blue,
+                aqua: Color::Cyan,
+                orange: Color::Yellow,
+                selected_bg: None, // highlighted with reverse video
+                folder_icon: Color::Yellow,
+            },
+        }
+    }
+
+    /// Style for the highlighted row.
+    pub(crate) fn highlight_style(&self) -> Style {
+        match self.selected_bg {
+            Some(bg) => Style::default().bg(bg),
+            None => Style::default().add_modifier(Modifier::REVERSED),
+        }
+    }
+}
+
+/// Parse a color as "#rrggbb", an ANSI color name ("red", "lightblue", ...),
+/// "reset"/"default" or a 256-color palette index.
+fn parse_color(s: &str) -> Option<Color> {
+    let s = s.trim();
+    if let Some(hex) = s.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    if let Ok(idx) = s.parse::<u8>() {
+        return Some(Color::Indexed(idx));
+    }
+    let color = match s.to_lowercase().replace(['_', '-', ' '], "").as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" | "purple" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" | "white" => Color::Gray,
+        "darkgray" | "darkgrey" | "brightblack" => Color::DarkGray,
+        "lightred" | "brightred" => Color::LightRed,
+        "lightgreen" | "brightgreen" => Color::LightGreen,
+        "lightyellow" | "brightyellow" => Color::LightYellow,
+        "lightblue" | "brightblue" => Color::LightBlue,
+        "lightmagenta" | "brightmagenta" => Color::LightMagenta,
+        "lightcyan" | "brightcyan" => Color::LightCyan,
+        "brightwhite" => Color::White,
+        _ => return None,
+    };
+    Some(color)
+}
+
+/// A user-defined theme file in `~/.config/rpai/themes/*.json`.
+#[derive(Debug, Deserialize)]
+struct ThemeFile {
+    pub(crate) name: String,
+    pub(crate) fg: String,
+    pub(crate) dim: String,
+    pub(crate) accent: String,
+    pub(crate) green: String,
+    pub(crate) blue: String,
+    pub(crate) aqua: String,
+    pub(crate) orange: String,
+    selected_bg: String,
+    pub(crate) folder_icon: String,
+}
+
+impl ThemeFile {
+    fn to_theme(&self) -> std::result::Result<Theme, String> {
+        let color = |slot: &str, value: &str| {
+            parse_color(value).ok_or_else(|| format!("invalid color for {}: '{}'", slot, value))
+        };
+        Ok(Theme {
+            fg: color("fg", &self.fg)?,
+            dim: color("dim", &self.dim)?,
+            accent: color("accent", &self.accent)?,
+            green: color("green", &self.green)?,
+            blue: color("blue", &self.blue)?,
+            aqua: color("aqua", &self.aqua)?,
+            orange: color("orange", &self.orange)?,
+            selected_bg: if self.selected_bg.trim().eq_ignore_ascii_case("reverse") {
+                None
+            } else {
+                Some(color("selected_bg", &self.selected_bg)?)
+            },
+            folder_icon: color("folder_icon", &self.folder_icon)?,
+        })
+    }
+}
+
+/// Built-in themes plus the user's custom themes, addressed by name.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ThemeSet {
+    pub(crate) custom: Vec<(String, Theme)>,
+    /// Problems found while loading custom themes, one message per file
+    pub(crate) errors: Vec<String>,
+}
+
+impl ThemeSet {
+    pub(crate) fn load() -> Self {
+        let mut set = ThemeSet::default();
+        let dir = config_dir().join("themes");
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return set;
+        };
+
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let parsed = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|c| serde_json::from_str::<ThemeFile>(&c).map_err(|e| e.to_string()))
+                .and_then(|file| {
+                    let name = file.name.trim().to_lowercase();
+                    if name.is_empty() {
+                        Err("theme name is empty".to_string())
+                    } else if ThemeName::parse(&name).is_some() {
+                        Err(format!("'{}' is a built-in theme name", name))
+                    } else if set.custom.iter().any(|(n, _)| *n == name) {
+                        Err(format!("duplicate theme name '{}'", name))
+                    } else {
+                        file.to_theme().map(|theme| (name, theme))
+                    }
+                });
+            match parsed {
+                Ok(entry) => set.custom.push(entry),
+                Err(e) => set.errors.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+        set
+    }
+
+    pub(crate) fn names(&self) -> Vec<String> {
+        ThemeName::all()
+            .iter()
+            .map(|t| t.name().to_string())
+            .chain(self.custom.iter().map(|(n, _)| n.clone()))
+            .collect()
+    }
+
+    /// Resolve a theme name (including built-in aliases) to its canonical name and colors.
+    pub(crate) fn get(&self, name: &str) -> Option<(String, Theme)> {
+        if let Some(builtin) = ThemeName::parse(name) {
+            return Some((builtin.name().to_string(), Theme::from_name(builtin)));
+        }
+        let name = name.to_lowercase();
+        self.custom.iter().find(|(n, _)| *n == name).cloned()
+    }
+
+    pub(crate) fn next(&self, current: &str) -> String {
+        let names = self.names();
+        let idx = names.iter().position(|n| n == current).unwrap_or(0);
+        names[(idx + 1) % names.len()].clone()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColorSupport {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+    /// NO_COLOR: monochrome, using only bold, reverse and dim
+    None,
+}
+
+impl ColorSupport {
+    pub(crate) fn parse(s: &str) -> Option<ColorSupport> {
+        match s.to_lowercase().as_str() {
+            "truecolor" | "24bit" | "rgb" => Some(ColorSupport::TrueColor),
+            "256" | "ansi256" => Some(ColorSupport::Ansi256),
+            "16" | "ansi16" | "ansi" => Some(ColorSupport::Ansi16),
+            "none" | "mono" | "monochrome" => Some(ColorSupport::None),
+            _ => None,
+        }
+    }
+
+    /// Resolve the `color` config option, detecting the terminal for "auto".
+    pub(crate) fn resolve(setting: &str) -> ColorSupport {
+        Self::parse(setting).unwrap_or_else(detect_color_support)
+    }
+}
+
+/// Detect color capability from NO_COLOR, COLORTERM, TERM and, inside tmux,
+/// the attached client's `client_termfeatures`.
+fn detect_color_support() -> ColorSupport {
+    if env::var("NO_COLOR").is_ok_and(|v| !v.is_empty()) {
+        return ColorSupport::None;
+    }
+
+    let term = env::var("TERM").unwrap_or_default().to_lowercase();
+    if term == "dumb" {
+        return ColorSupport::None;
+    }
+    let term_256 = term.contains("256color");
+
+    // tmux only passes RGB through when the outer terminal has the Tc/RGB feature,
+    // regardless of what COLORTERM says
+    if env::var("TMUX").is_ok() {
+        if let Ok(output) = Command::new("tmux")
+            .args(["display-message", "-p", "#{client_termfeatures}"])
+            .output()
+        {
+            if output.status.success() {
+                let features = String::from_utf8_lossy(&output.stdout).to_lowercase();
+                return if features.split(',').any(|f| f.trim() == "rgb") {
+                    ColorSupport::TrueColor
+                } else if term_256 {
+                    ColorSupport::Ansi256
+                } else {
+                    ColorSupport::Ansi16
+                };
+            }
+        }
+    }
+
+    let colorterm = env::var("COLORTERM").unwrap_or_default().to_lowercase();
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        ColorSupport::TrueColor
+    } else if term_256 {
+        ColorSupport::Ansi256
+    } else {
+        ColorSupport::Ansi16
+    }
+}
+
+/// The standard xterm values of the 16 base colors, in palette order.
+const ANSI16_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+const ANSI16_COLORS: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn color_distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+fn nearest_cube_level(v: u8) -> usize {
+    (0..CUBE_LEVELS.len())
+        .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - v as i32).abs())
+        .unwrap_or(0)
+}
+
+/// Map an RGB color to the closest entry of the 256-color cube or gray ramp.
+fn rgb_to_256(rgb: (u8, u8, u8)) -> u8 {
+    let (r, g, b) = (
+        nearest_cube_level(rgb.0),
+        nearest_cube_level(rgb.1),
+        nearest_cube_level(rgb.2),
+    );
+    let cube = (CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]);
+
+    let avg = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let gray_idx = (avg.saturating_sub(3) / 10).min(23) as u8;
+    let gray_level = 8 + 10 * gray_idx;
+
+    if color_distance(rgb, (gray_level, gray_level, gray_level)) < color_distance(rgb, cube) {
+        232 + gray_idx
+    } else {
+        16 + 36 * r as u8 + 6 * g as u8 + b as u8
+    }
+}
+
+fn indexed_to_rgb(idx: u8) -> (u8, u8, u8) {
+    match idx {
+        0..=15 => ANSI16_RGB[idx as usize],
+        16..=231 => {
+            let i = idx - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[((i / 6) % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + 10 * (idx - 232);
+            (level, level, level)
+        }
+    }
+}
+
+fn rgb_to_16(rgb: (u8, u8, u8)) -> Color {
+    let idx = (0..ANSI16_RGB.len())
+        .min_by_key(|&i| color_distance(rgb, ANSI16_RGB[i]))
+        .unwrap_or(7);
+    ANSI16_COLORS[idx]
+}
+
+/// Downsample a color to what the terminal can display.
+fn adapt_color(color: Color, support: ColorSupport) -> Color {
+    match (support, color) {
+        (ColorSupport::None, _) => Color::Reset,
+        (ColorSupport::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(rgb_to_256((r, g, b))),
+        (ColorSupport::Ansi16, Color::Rgb(r, g, b)) => rgb_to_16((r, g, b)),
+        (ColorSupport::Ansi16, Color::Indexed(i)) if i >= 16 => rgb_to_16(indexed_to_rgb(i)),
+        _ => color,
+    }
+}
+
+/// Rewrite a rendered buffer for the terminal's color capability. In monochrome
+/// mode, the theme's roles are expressed with modifiers instead: dim text is
+/// dimmed, accents are bold and the selection background is reversed.
+pub(crate) fn adapt_buffer_colors(buf: &mut Buffer, support: ColorSupport, theme: &Theme) {
+    if support == ColorSupport::TrueColor {
+        return;
+    }
+    for cell in buf.content.iter_mut() {
+        if support == ColorSupport::None {
+            let mut modifier = cell.modifier;
+            if cell.fg == theme.dim && cell.fg != Color::Reset {
+                modifier |= Modifier::DIM;
+            } else if cell.fg == theme.accent || cell.fg == theme.green {
+                modifier |= Modifier::BOLD;
+            }
+            if theme.selected_bg == Some(cell.bg) && cell.bg != Color::Reset {
+                modifier |= Modifier::REVERSED;
+            }
+            cell.modifier = modifier;
+        }
+        cell.fg = adapt_color(cell.fg, support);
+        cell.bg = adapt_color(cell.bg, support);
+    }
+}
+
+/// A color in tmux's style syntax (`#rrggbb` or `colourN`), downsampled like
+/// the TUI. None for the terminal's default color.
+pub(crate) fn tmux_color(color: Color, support: ColorSupport) -> Option<String> {
+    match adapt_color(color, support) {
+        Color::Reset => None,
+        Color::Rgb(r, g, b) => Some(format!("#{:02x}{:02x}{:02x}", r, g, b)),
+        Color::Indexed(i) => Some(format!("colour{}", i)),
+        base => ANSI16_COLORS
+            .iter()
+            .position(|c| *c == base)
+            .map(|i| format!("colour{}", i)),
+    }
+}
+
+/// Resolve the configured theme, falling back to gruvbox for unknown names.
+pub(crate) fn load_theme(themes: &ThemeSet, config: &Config) -> (String, Theme) {
+    themes.get(&config.theme).unwrap_or_else(|| {
+        (
+            ThemeName::Gruvbox.name().to_string(),
+            Theme::from_name(ThemeName::Gruvbox),
+        )
+    })
+}
+
+pub(crate) fn save_theme(name: &str) -> Result<()> {
+    let mut config = Config::load();
+    config.theme = name.to_string();
+    Ok(config.save()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgb_downsamples_to_256_colors() {
+        for (rgb, expected) in [
+            ((0, 0, 0), 16),
+            ((255, 255, 255), 231),
+            ((255, 0, 0), 196),
+            ((0, 95, 135), 24),
+            ((250, 189, 47), 214),
+            ((128, 128, 128), 244),
+            ((8, 8, 8), 232),
+            ((80, 73, 69), 239),
+        ] {
+            assert_eq!(rgb_to_256(rgb), expected, "{:?}", rgb);
+        }
+    }
+
+    #[test]
+    fn rgb_downsamples_to_16_colors() {
+        for (rgb, expected) in [
+            ((0, 0, 0), Color::Black),
+            ((255, 255, 255), Color::White),
+            ((200, 10, 10), Color::Red),
+            ((255, 80, 80), Color::LightRed),
+            ((184, 187, 38), Color::Yellow),
+            ((131, 165, 152), Color::DarkGray),
+            ((90, 90, 250), Color::LightBlue),
+            ((0, 210, 200), Color::Cyan),
+        ] {
+            assert_eq!(rgb_to_16(rgb), expected, "{:?}", rgb);
+        }
+    }
+
+    #[test]
+    fn custom_theme_selection_is_explicit() {
+        let file = |selected_bg: &str| ThemeFile {
+            name: "mine".to_string(),
+            fg: "white".to_string(),
+            dim: "darkgray".to_string(),
+            accent: "magenta".to_string(),
+            green: "green".to_string(),
+            blue: "blue".to_string(),
+            aqua: "cyan".to_string(),
+            orange: "yellow".to_string(),
+            selected_bg: selected_bg.to_string(),
+            folder_icon: "yellow".to_string(),
+        };
+        let style = |selected_bg| file(selected_bg).to_theme().unwrap().highlight_style();
+        assert_eq!(style("reset"), Style::default().bg(Color::Reset));
+        assert_eq!(
+            style("reverse"),
+            Style::default().add_modifier(Modifier::REVERSED)
+        );
+        assert!(file("nope").to_theme().is_err());
+    }
+}
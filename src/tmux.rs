@@ -0,0 +1,356 @@
+//! tmux integration: mapping processes to panes, focusing panes and the
+//! recently visited pane list.
+
+use crate::backend::Pane;
+use crate::config::{config_dir, ensure_config_dir};
+use crate::error::{run_command, Error, Result};
+use crate::session::{AiSession, MultiplexerKind};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::os::unix::fs::FileTypeExt;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Number of panes kept in the recently visited list.
+const MRU_LIMIT: usize = 50;
+
+/// A pane on a particular tmux server. Pane ids are only unique within a
+/// server, so the visited, zoomed and pulled pane lists key panes by both.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerPane {
+    /// Socket path of the server
+    pub socket: String,
+    /// Pane id, e.g. "%3"
+    pub pane: String,
+}
+
+impl ServerPane {
+    /// Pane `pane` on the server at `socket`. None stands for the server plain
+    /// `tmux` commands reach: the current one inside tmux, the default one outside.
+    pub fn new(socket: Option<&str>, pane: &str) -> Self {
+        Self {
+            socket: server_socket(socket),
+            pane: pane.to_string(),
+        }
+    }
+
+    /// The pane a session runs in, if it is in one.
+    pub fn of(session: &AiSession) -> Option<Self> {
+        let pane = session.pane_id.as_deref()?;
+        Some(Self::new(session.mux_socket.as_deref(), pane))
+    }
+
+    /// Whether this is pane `pane` on the server at `socket`.
+    pub fn is(&self, socket: Option<&str>, pane: &str) -> bool {
+        self.pane == pane && same_server(Some(&self.socket), socket)
+    }
+
+    /// Whether `session` runs in this pane.
+    pub fn holds(&self, session: &AiSession) -> bool {
+        session
+            .pane_id
+            .as_deref()
+            .is_some_and(|pane| self.is(session.mux_socket.as_deref(), pane))
+    }
+}
+
+/// Recently visited panes, most recent first, from `~/.config/rpai/mru.json`.
+pub fn load_mru() -> Vec<ServerPane> {
+    fs::read_to_string(config_dir().join("mru.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Record a jump from pane `from` to pane `to`, leaving `to` on top of the MRU
+/// stack with `from` right below it, so `rpai back` returns to `from`.
+pub fn record_visit(from: Option<&ServerPane>, to: &ServerPane) {
+    let mut mru = load_mru();
+    mru.retain(|p| p != to && Some(p) != from);
+    if let Some(from) = from.filter(|f| *f != to) {
+        mru.insert(0, from.clone());
+    }
+    mru.insert(0, to.clone());
+    mru.truncate(MRU_LIMIT);
+    if let (Ok(dir), Ok(content)) = (ensure_config_dir(), serde_json::to_string(&mru)) {
+        let _ = fs::write(dir.join("mru.json"), content);
+    }
+}
+
+/// The most recently visited pane other than `current`.
+pub fn previous_visited_pane(current: Option<&ServerPane>) -> Option<ServerPane> {
+    load_mru().into_iter().find(|p| Some(p) != current)
+}
+
+/// The user's tmux socket directory, `$TMUX_TMPDIR/tmux-<uid>` (default `/tmp`).
+pub(crate) fn socket_dir() -> PathBuf {
+    static UID: OnceLock<String> = OnceLock::new();
+    let uid = UID.get_or_init(|| run_command("id", &["-u"]).unwrap_or_default());
+    env::var_os("TMUX_TMPDIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(format!("tmux-{}", uid))
+}
+
+/// Resolve a configured socket: a path as given (with `~` expanded), or a
+/// bare name as for `tmux -L`, in [`socket_dir`].
+pub(crate) fn socket_path(socket: &str) -> String {
+    if let (Some(rest), Ok(home)) = (socket.strip_prefix("~/"), env::var("HOME")) {
+        return format!("{}/{}", home, rest);
+    }
+    if socket.contains('/') {
+        socket.to_string()
+    } else {
+        socket_dir().join(socket).display().to_string()
+    }
+}
+
+/// The socket of the tmux server this process runs in, from `$TMUX`.
+pub(crate) fn current_socket() -> Option<String> {
+    let tmux = env::var("TMUX").ok()?;
+    tmux.split(',')
+        .next()
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+/// The socket of the server at `socket`. None stands for the server plain
+/// `tmux` commands reach: the current one inside tmux, the default one outside.
+pub(crate) fn server_socket(socket: Option<&str>) -> String {
+    match socket {
+        Some(socket) => socket.to_string(),
+        None => current_socket().unwrap_or_else(|| socket_path("default")),
+    }
+}
+
+/// Whether sockets `a` and `b` belong to the same server, None standing for
+/// the server plain `tmux` commands reach.
+pub fn same_server(a: Option<&str>, b: Option<&str>) -> bool {
+    let (a, b) = (server_socket(a), server_socket(b));
+    a == b || fs::canonicalize(&a).is_ok_and(|a| fs::canonicalize(&b).is_ok_and(|b| a == b))
+}
+
+/// Sockets of the user's tmux servers: every socket in [`socket_dir`], plus
+/// the current server's and those in `extra` (see [`socket_path`]) that exist.
+pub(crate) fn discover_sockets(extra: &[String]) -> Vec<String> {
+    let mut sockets: Vec<String> = fs::read_dir(socket_dir())
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|e| e.file_type().is_ok_and(|t| t.is_socket()))
+                .map(|e| e.path().display().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    sockets.sort();
+    let more = current_socket()
+        .into_iter()
+        .chain(extra.iter().map(|s| socket_path(s)));
+    for socket in more {
+        let is_socket = fs::metadata(&socket).is_ok_and(|m| m.file_type().is_socket());
+        if is_socket && !sockets.contains(&socket) {
+            sockets.push(socket);
+        }
+    }
+    sockets
+}
+
+/// Every pane of every tmux server in the socket directory, the current one
+/// and those at `sockets` (paths, or names as for `tmux -L`), keyed by the PID
+/// of the process running in it. Empty when no server is running.
+pub fn get_tmux_pane_info(sockets: &[String]) -> Result<HashMap<u32, Pane>> {
+    all_panes(&discover_sockets(sockets))
+}
+
+/// Panes of the servers at `sockets`, or of the default server when there are none.
+pub(crate) fn all_panes(sockets: &[String]) -> Result<HashMap<u32, Pane>> {
+    if sockets.is_empty() {
+        return panes_on(None);
+    }
+    let mut panes = HashMap::new();
+    for socket in sockets {
+        // Stale sockets of servers that have exited are skipped
+        panes.extend(panes_on(Some(socket)).unwrap_or_default());
+    }
+    Ok(panes)
+}
+
+/// Every pane of one tmux server, keyed by the PID of the process running in it.
+pub(crate) fn panes_on(socket: Option<&str>) -> Result<HashMap<u32, Pane>> {
+    let output = command(socket)
+        .args([
+            "list-panes",
+            "-a",
+            "-F",
+            "#{pane_pid}\t#{pane_id}\t#{session_name}\t#{window_index}\t#{pane_width}\t#{pane_height}",
+        ])
+        .output()
+        .map_err(|e| Error::Command {
+            command: "tmux list-panes".to_string(),
+            message: e.to_string(),
+        })?;
+
+    let mut pane_map: HashMap<u32, Pane> = HashMap::new();
+
+    if output.status.success() {
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let parts: Vec<&str> = line.split('\t').collect();
+            if parts.len() >= 6 {
+                let pid: u32 = parts.first().and_then(|s| s.parse().ok()).unwrap_or(0);
+
+                if pid > 0 {
+                    let pane_info = Pane {
+                        multiplexer: MultiplexerKind::Tmux,
+                        pane_id: parts.get(1).unwrap_or(&"").to_string(),
+                        session: parts.get(2).unwrap_or(&"").to_string(),
+                        window: parts.get(3).map(|s| s.to_string()),
+                        width: parts.get(4).and_then(|s| s.parse().ok()),
+                        height: parts.get(5).and_then(|s| s.parse().ok()),
+                        socket: socket.map(str::to_string),
+                    };
+                    pane_map.insert(pid, pane_info);
+                }
+            }
+        }
+    }
+
+    Ok(pane_map)
+}
+
+/// Run a tmux command, returning its trimmed stdout.
+pub fn run(args: &[&str]) -> Result<String> {
+    run_command("tmux", args)
+}
+
+/// Run a tmux command against the server at `socket` (`tmux -S`), or the
+/// default server when None.
+pub fn run_on(socket: Option<&str>, args: &[&str]) -> Result<String> {
+    match socket {
+        Some(socket) => {
+            let mut full = vec!["-S", socket];
+            full.extend_from_slice(args);
+            run_command("tmux", &full)
+        }
+        None => run(args),
+    }
+}
+
+/// Quote `s` for a POSIX shell.
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// A `tmux` command for the server at `socket`, or the default server.
+pub(crate) fn command(socket: Option<&str>) -> Command {
+    let mut command = Command::new("tmux");
+    if let Some(socket) = socket {
+        command.args(["-S", socket]);
+    }
+    command
+}
+
+/// Whether `socket` is the server this process runs in. None stands for the
+/// server plain `tmux` commands reach, which is the current one inside tmux.
+pub fn is_current_server(socket: Option<&str>) -> bool {
+    match (socket, current_socket()) {
+        (None, _) => true,
+        (Some(socket), Some(current)) => {
+            socket == current || fs::canonicalize(socket).ok() == fs::canonicalize(&current).ok()
+        }
+        (Some(_), None) => false,
+    }
+}
+
+/// Panes rpai zoomed on arrival, so `rpai back` can unzoom them when leaving.
+fn load_zoomed_panes() -> Vec<ServerPane> {
+    fs::read_to_string(config_dir().join("zoomed.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_zoomed_panes(panes: &[ServerPane]) {
+    if let (Ok(dir), Ok(content)) = (ensure_config_dir(), serde_json::to_string(panes)) {
+        let _ = fs::write(dir.join("zoomed.json"), content);
+    }
+}
+
+/// Make `pane_id` the active pane of its window on the server at `socket`
+/// and, when `zoom` is set, zoom it unless its window is already zoomed.
+pub fn focus_pane(
+    socket: Option<&str>,
+    window_target: &str,
+    pane_id: &str,
+    zoom: bool,
+) -> Result<()> {
+    run_on(socket, &["select-window", "-t", window_target])?;
+    run_on(socket, &["select-pane", "-t", pane_id])?;
+
+    if zoom {
+        let zoomed = run_on(
+            socket,
+            &[
+                "display-message",
+                "-p",
+                "-t",
+                pane_id,
+                "#{window_zoomed_flag}",
+            ],
+        )?;
+        if zoomed != "1" {
+            run_on(socket, &["resize-pane", "-Z", "-t", pane_id])?;
+            let zoomed = ServerPane::new(socket, pane_id);
+            let mut panes = load_zoomed_panes();
+            if !panes.contains(&zoomed) {
+                panes.push(zoomed);
+                save_zoomed_panes(&panes);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Undo a zoom rpai applied to `pane` on arrival, if it is still zoomed.
+pub fn restore_zoom(pane: &ServerPane) {
+    let mut panes = load_zoomed_panes();
+    let Some(pos) = panes
+        .iter()
+        .position(|p| p.is(Some(&pane.socket), &pane.pane))
+    else {
+        return;
+    };
+    panes.remove(pos);
+    save_zoomed_panes(&panes);
+
+    let socket = Some(pane.socket.as_str());
+    let zoomed = run_on(
+        socket,
+        &[
+            "display-message",
+            "-p",
+            "-t",
+            &pane.pane,
+            "#{window_zoomed_flag}",
+        ],
+    );
+    if zoomed.is_ok_and(|z| z == "1") {
+        let _ = run_on(socket, &["resize-pane", "-Z", "-t", &pane.pane]);
+    }
+}
+
+/// The pane rpai is running in, when inside tmux.
+pub fn current_tmux_pane() -> Option<String> {
+    env::var("TMUX").ok()?;
+    let output = Command::new("tmux")
+        .args(["display-message", "-p", "#{pane_id}"])
+        .output()
+        .ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        None
+    }
+}
@@ -0,0 +1,1231 @@
+//! Rendering the TUI.
+
+use crate::app::{App, AppMode, Confirm, View, DASHBOARD_MIN_CELL_WIDTH};
+use crate::grouping::{ListRow, SessionGroup};
+use crate::keys::{Action, KeyBindings};
+use crate::layout::{format_tokens, Column, ColumnData, ListLayout};
+use crate::theme::{adapt_buffer_colors, Theme};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+    Frame,
+};
+use rpai::support::{format_duration, unix_now};
+use rpai::{AiSession, Config, ListeningPort, SessionState};
+use std::env;
+
+// Format path with visual hierarchy
+fn format_path_visual(path: &str, max_len: usize, theme: &Theme) -> Vec<Span<'static>> {
+    // Handle home directory
+    let home = env::var("HOME").ok();
+    let display_path = if let Some(ref home_path) = home {
+        if path.starts_with(home_path) {
+            format!("~{}", &path[home_path.len()..])
+        } else {
+            path.to_string()
+        }
+    } else {
+        path.to_string()
+    };
+
+    let parts: Vec<&str> = display_path.split('/').filter(|s| !s.is_empty()).collect();
+
+    if parts.is_empty() {
+        return vec![Span::styled(
+            path.to_string(),
+            Style::default().fg(theme.dim),
+        )];
+    }
+
+    // Calculate if we need to truncate
+    let total_len: usize = parts.iter().map(|p| p.len() + 3).sum(); // +3 for " / "
+
+    let mut spans = Vec::new();
+
+    // Folder icon
+    spans.push(Span::styled(" ", Style::default().fg(theme.folder_icon)));
+
+    if total_len > max_len && parts.len() > 2 {
+        // Show first part, ..., last two parts
+        spans.push(Span::styled(
+            format!(" {}", parts[0]),
+            Style::default().fg(theme.fg),
+        ));
+        spans.push(Span::styled(" / ... / ", Style::default().fg(theme.dim)));
+
+        let last_idx = parts.len() - 1;
+        if parts.len() > 2 {
+            spans.push(Span::styled(
+                parts[last_idx - 1].to_string(),
+                Style::default().fg(theme.fg),
+            ));
+            spans.push(Span::styled(" / ", Style::default().fg(theme.dim)));
+        }
+        spans.push(Span::styled(
+            parts[last_idx].to_string(),
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        ));
+    } else {
+        // Show full path with styled separators
+        for (i, part) in parts.iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::styled(" / ", Style::default().fg(theme.dim)));
+            } else {
+                spans.push(Span::styled(" ", Style::default()));
+            }
+
+            // Last part is brighter/accented
+            let style = if i == parts.len() - 1 {
+                Style::default()
+                    .fg(theme.accent)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(theme.fg)
+            };
+            spans.push(Span::styled(part.to_string(), style));
+        }
+    }
+
+    spans
+}
+
+/// Whether a session is over the configured `memory_warning_mb`.
+pub(crate) fn memory_warning(session: &AiSession, config: &Config) -> bool {
+    config.memory_warning_mb > 0 && session.memory_mb >= config.memory_warning_mb
+}
+
+fn memory_style(session: &AiSession, theme: &Theme, config: &Config) -> Style {
+    if memory_warning(session, config) {
+        Style::default()
+            .fg(theme.orange)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(theme.fg)
+    }
+}
+
+pub(crate) fn ui(frame: &mut Frame, app: &mut App) {
+    let area = frame.area();
+
+    // Main layout: header, list, status/command, help bar
+    let chunks = Layout::vertical([
+        Constraint::Length(3), // Header
+        Constraint::Min(5),    // List
+        Constraint::Length(1), // Status/command line
+        Constraint::Length(2), // Help bar
+    ])
+    .split(area);
+
+    // Session list first: the dashboard and detail views need `&mut App`
+    if app.sessions.is_empty() {
+        let empty = Paragraph::new(Line::from(vec![Span::styled(
+            "  No AI agent processes detected",
+            Style::default().fg(app.theme.orange),
+        )]))
+        .block(Block::default());
+        frame.render_widget(empty, chunks[1]);
+    } else if app.view == View::Dashboard {
+        render_dashboard(frame, chunks[1], app);
+    } else if app.view == View::Detail {
+        render_details(frame, chunks[1], app);
+    } else {
+        let selected = app.list_state.selected();
+        let items: Vec<ListItem> = app
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(row, list_row)| {
+                let is_selected = selected == Some(row);
+                match *list_row {
+                    ListRow::Group(g) => {
+                        let group = &app.groups[g];
+                        create_group_header_item(
+                            group,
+                            &app.sessions,
+                            app.collapsed.contains(&group.key),
+                            is_selected,
+                            &app.theme,
+                            &app.config,
+                        )
+                    }
+                    ListRow::Session(i) => create_session_list_item(
+                        &app.sessions[i],
+                        i,
+                        is_selected,
+                        chunks[1].width,
+                        app,
+                    ),
+                }
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default())
+            .highlight_style(app.theme.highlight_style());
+
+        frame.render_stateful_widget(list, chunks[1], &mut app.list_state);
+    }
+
+    let theme = &app.theme;
+
+    // Header
+    let header = Paragraph::new(Line::from(vec![
+        Span::styled(
+            " rpai ",
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            match app.view {
+                View::Dashboard => "- Dashboard",
+                View::Detail => "- Session Details",
+                View::List => "- AI Agent Sessions",
+            },
+            Style::default().fg(theme.fg),
+        ),
+    ]))
+    .block(
+        Block::default()
+            .borders(Borders::BOTTOM)
+            .border_style(Style::default().fg(theme.dim)),
+    );
+    frame.render_widget(header, chunks[0]);
+
+    // Status/command line
+    let status_line = match &app.mode {
+        AppMode::Command => Paragraph::new(Line::from(vec![
+            Span::styled(":", Style::default().fg(theme.accent)),
+            Span::styled(app.command_input.clone(), Style::default().fg(theme.fg)),
+            Span::styled("_", Style::default().fg(theme.accent)),
+        ])),
+        AppMode::Confirm => Paragraph::new(Line::from(Span::styled(
+            format!(
+                " {}",
+                app.confirm
+                    .as_ref()
+                    .map(Confirm::prompt)
+                    .unwrap_or_default()
+            ),
+            Style::default()
+                .fg(theme.orange)
+                .add_modifier(Modifier::BOLD),
+        ))),
+        AppMode::Normal => {
+            if let Some(msg) = &app.status_message {
+                let color = if app.status_is_error {
+                    theme.orange
+                } else {
+                    theme.aqua
+                };
+                Paragraph::new(Line::from(vec![Span::styled(
+                    format!(" {}", msg),
+                    Style::default().fg(color),
+                )]))
+            } else {
+                Paragraph::new("")
+            }
+        }
+    };
+    frame.render_widget(status_line, chunks[2]);
+
+    // Help bar
+    let help_spans = if let (AppMode::Command, Some(completion)) = (&app.mode, &app.completion) {
+        // Show the completion candidates, highlighting the current one
+        let mut spans = vec![Span::styled(" ", Style::default())];
+        for (i, candidate) in completion.candidates.iter().enumerate() {
+            let style = if i == completion.index {
+                Style::default()
+                    .fg(theme.accent)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(theme.dim)
+            };
+            spans.push(Span::styled(candidate.clone(), style));
+            spans.push(Span::styled("  ", Style::default()));
+        }
+        spans
+    } else if app.mode == AppMode::Confirm {
+        vec![
+            Span::styled(" y", Style::default().fg(theme.green)),
+            Span::styled(" confirm  ", Style::default().fg(theme.dim)),
+            Span::styled("any other key", Style::default().fg(theme.green)),
+            Span::styled(" cancel", Style::default().fg(theme.dim)),
+        ]
+    } else if app.mode == AppMode::Command {
+        vec![
+            Span::styled(" Enter", Style::default().fg(theme.green)),
+            Span::styled(" execute  ", Style::default().fg(theme.dim)),
+            Span::styled("Tab", Style::default().fg(theme.green)),
+            Span::styled(" complete  ", Style::default().fg(theme.dim)),
+            Span::styled("Up/Down", Style::default().fg(theme.green)),
+            Span::styled(" history  ", Style::default().fg(theme.dim)),
+            Span::styled("Esc", Style::default().fg(theme.green)),
+            Span::styled(" cancel", Style::default().fg(theme.dim)),
+        ]
+    } else {
+        help_bar_spans(&app.bindings, theme, app.view)
+    };
+
+    let help = Paragraph::new(Line::from(help_spans)).block(
+        Block::default()
+            .borders(Borders::TOP)
+            .border_style(Style::default().fg(theme.dim)),
+    );
+    frame.render_widget(help, chunks[3]);
+
+    adapt_buffer_colors(frame.buffer_mut(), app.color_support, theme);
+}
+
+/// Columns and rows of the dashboard grid for `count` sessions in `area`:
+/// roughly square, but never narrower than `DASHBOARD_MIN_CELL_WIDTH`.
+fn dashboard_grid(count: usize, area: Rect) -> (usize, usize) {
+    if count == 0 {
+        return (1, 0);
+    }
+    let square = (count as f64).sqrt().ceil() as usize;
+    let max_cols = (area.width / DASHBOARD_MIN_CELL_WIDTH).max(1) as usize;
+    let cols = square.min(max_cols).max(1);
+    (cols, count.div_ceil(cols))
+}
+
+fn render_dashboard(frame: &mut Frame, area: Rect, app: &mut App) {
+    let visible = app.dashboard_sessions();
+    let (cols, rows) = dashboard_grid(visible.len(), area);
+    app.dashboard_cols = cols;
+    if visible.is_empty() {
+        return;
+    }
+    let selected = app.dashboard_position(&visible);
+    app.dashboard_pid = Some(app.sessions[visible[selected]].pid);
+
+    // Keep cells at least 5 lines tall, scrolling to the selected row when needed
+    let rows_on_screen = rows.min((area.height / 5).max(1) as usize);
+    let selected_row = selected / cols;
+    let first_row = selected_row.saturating_sub(rows_on_screen - 1);
+
+    let row_areas = Layout::vertical(vec![
+        Constraint::Ratio(1, rows_on_screen as u32);
+        rows_on_screen
+    ])
+    .split(area);
+    for (r, row_area) in row_areas.iter().enumerate() {
+        let cell_areas =
+            Layout::horizontal(vec![Constraint::Ratio(1, cols as u32); cols]).split(*row_area);
+        for (c, cell_area) in cell_areas.iter().enumerate() {
+            let pos = (first_row + r) * cols + c;
+            let Some(&i) = visible.get(pos) else {
+                continue;
+            };
+            render_dashboard_cell(frame, *cell_area, app, i, pos == selected);
+        }
+    }
+}
+
+fn render_dashboard_cell(frame: &mut Frame, area: Rect, app: &App, idx: usize, is_selected: bool) {
+    let theme = &app.theme;
+    let session = &app.sessions[idx];
+    let state_color = if session.state == SessionState::Running {
+        theme.green
+    } else {
+        theme.orange
+    };
+    let name = session
+        .name
+        .clone()
+        .or_else(|| {
+            let mux_session = session.mux_session.as_ref()?;
+            Some(match &session.mux_window {
+                Some(window) => format!("{}:{}", mux_session, window),
+                None => mux_session.clone(),
+            })
+        })
+        .unwrap_or_else(|| "not in tmux".to_string());
+
+    let title = Line::from(vec![
+        Span::styled(
+            format!(" {} ", session.state.symbol(app.config.ascii_symbols)),
+            Style::default().fg(state_color),
+        ),
+        Span::styled(
+            format!("{} {} ", session.id, session.agent_type),
+            Style::default().fg(theme.aqua).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(format!("{} ", name), Style::default().fg(theme.fg)),
+        Span::styled(
+            format!("CPU: {:.1}% ", session.cpu_percent),
+            Style::default().fg(state_color),
+        ),
+    ]);
+    let border_style = if is_selected {
+        Style::default()
+            .fg(theme.accent)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(theme.dim)
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(border_style)
+        .title(title);
+
+    let inner_height = area.height.saturating_sub(2) as usize;
+    let lines: Vec<Line> = match app.pane_captures.get(&session.pid) {
+        Some(captured) => captured[captured.len().saturating_sub(inner_height)..]
+            .iter()
+            .map(|l| Line::from(Span::styled(l.clone(), Style::default().fg(theme.fg))))
+            .collect(),
+        None => vec![Line::from(Span::styled(
+            if session.pane_id.is_some() {
+                "(no output captured)"
+            } else {
+                "(not in tmux)"
+            },
+            Style::default().fg(theme.dim),
+        ))],
+    };
+
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn render_details(frame: &mut Frame, area: Rect, app: &mut App) {
+    let theme = &app.theme;
+    let label =
+        |text: &str| Span::styled(format!("  {:<10}", text), Style::default().fg(theme.dim));
+    let value = |text: String| Span::styled(text, Style::default().fg(theme.fg));
+    let mut lines: Vec<Line> = Vec::new();
+
+    let Some(session) = app.detail_session_index().map(|i| &app.sessions[i]) else {
+        lines.push(Line::from(Span::styled(
+            "  Session has exited",
+            Style::default().fg(theme.orange),
+        )));
+        frame.render_widget(Paragraph::new(lines), area);
+        return;
+    };
+    let Some(details) = &app.details else {
+        lines.push(Line::from(Span::styled(
+            "  Reading process details...",
+            Style::default().fg(theme.dim),
+        )));
+        frame.render_widget(Paragraph::new(lines), area);
+        return;
+    };
+
+    let state_color = if session.state == SessionState::Running {
+        theme.green
+    } else {
+        theme.orange
+    };
+    lines.push(Line::from(vec![
+        Span::styled(
+            format!(" {} ", session.state.symbol(app.config.ascii_symbols)),
+            Style::default().fg(state_color),
+        ),
+        Span::styled(
+            session.agent_type.clone(),
+            Style::default().fg(theme.aqua).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            session
+                .name
+                .as_ref()
+                .map(|n| format!(" \"{}\"", n))
+                .unwrap_or_default(),
+            Style::default().fg(theme.fg),
+        ),
+    ]));
+    lines.push(Line::from(""));
+
+    let process = details.process.as_ref();
+    lines.push(Line::from(vec![
+        label("PID"),
+        value(details.pid.to_string()),
+    ]));
+    lines.push(Line::from(vec![
+        label("Command"),
+        value(process.map(|p| p.command.clone()).unwrap_or_default()),
+    ]));
+    lines.push(Line::from(vec![
+        label("Started"),
+        value(format!(
+            "{} ({} ago)",
+            process.map(|p| p.started.as_str()).unwrap_or("?"),
+            format_duration(session.uptime_seconds)
+        )),
+    ]));
+    lines.push(Line::from(vec![
+        label("TTY"),
+        value(process.map(|p| p.tty.clone()).unwrap_or_default()),
+    ]));
+    lines.push(Line::from(vec![
+        label("Directory"),
+        value(session.working_dir.clone()),
+    ]));
+    lines.push(Line::from(vec![
+        label("State"),
+        Span::styled(
+            format!(
+                "{} for {}",
+                session.state.name(),
+                format_duration(unix_now() - session.state_since)
+            ),
+            Style::default().fg(state_color),
+        ),
+    ]));
+    if let Some(activity) = &session.activity {
+        lines.push(Line::from(vec![
+            label("Activity"),
+            Span::styled(activity.clone(), Style::default().fg(theme.green)),
+        ]));
+    }
+    lines.push(Line::from(vec![
+        label("CPU"),
+        value(format!(
+            "{:.1}% (agent and descendants, excluding LSP)",
+            session.cpu_percent
+        )),
+    ]));
+    let mut memory = vec![
+        label("Memory"),
+        Span::styled(
+            format!("RSS {}MB", session.memory_mb),
+            memory_style(session, theme, &app.config),
+        ),
+    ];
+    if let Some(pss) = details.pss_mb {
+        memory.push(value(format!("  PSS {}MB", pss)));
+    }
+    memory.push(value(format!("  LSP {}MB", session.lsp_memory_mb)));
+    lines.push(Line::from(memory));
+    lines.push(Line::from(vec![
+        label(
+            details
+                .pane
+                .as_ref()
+                .map_or("tmux", |p| p.multiplexer.name()),
+        ),
+        value(match &details.pane {
+            Some(pane) => format!(
+                "{}{} pane {}{}{}",
+                pane.session,
+                pane.window
+                    .as_deref()
+                    .map(|w| format!(":{}", w))
+                    .unwrap_or_default(),
+                pane.pane_id,
+                match (pane.width, pane.height) {
+                    (Some(width), Some(height)) => format!(" ({}x{})", width, height),
+                    _ => String::new(),
+                },
+                pane.socket
+                    .as_deref()
+                    .map(|s| format!(" on {}", s))
+                    .unwrap_or_default()
+            ),
+            None => "not in tmux".to_string(),
+        }),
+    ]));
+
+    if !session.ports.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            " Listening ports",
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        )));
+        for port in &session.ports {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  {:<8}", format!(":{}", port.port)),
+                    Style::default().fg(theme.accent),
+                ),
+                value(format!("{:<16}", port.name)),
+                Span::styled(
+                    format!("PID {:<8}", port.pid),
+                    Style::default().fg(theme.dim),
+                ),
+                Span::styled(port.url(), Style::default().fg(theme.blue)),
+            ]));
+        }
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        " Parent chain",
+        Style::default()
+            .fg(theme.accent)
+            .add_modifier(Modifier::BOLD),
+    )));
+    if details.ancestry.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "  (no tmux pane found)",
+            Style::default().fg(theme.dim),
+        )));
+    }
+    for (depth, row) in details.ancestry.iter().enumerate() {
+        let mut marks = Vec::new();
+        if depth == 0 {
+            marks.push("agent");
+        }
+        if depth + 1 == details.ancestry.len() {
+            marks.push("tmux pane");
+        }
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("  {}{:>7} ", "  ".repeat(depth), row.pid),
+                Style::default().fg(theme.dim),
+            ),
+            value(row.command.clone()),
+            Span::styled(
+                if marks.is_empty() {
+                    String::new()
+                } else {
+                    format!("  ({})", marks.join(", "))
+                },
+                Style::default().fg(theme.aqua),
+            ),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!(" Descendants ({})", details.descendants.len()),
+        Style::default()
+            .fg(theme.accent)
+            .add_modifier(Modifier::BOLD),
+    )));
+    if details.descendants.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "  (none)",
+            Style::default().fg(theme.dim),
+        )));
+    }
+    for node in &details.descendants {
+        let style = if node.is_lsp {
+            Style::default().fg(theme.dim)
+        } else {
+            Style::default().fg(theme.fg)
+        };
+        let mut spans = vec![
+            Span::styled(
+                format!(
+                    "  {:>7} {:>5.1}% {:>6}MB ",
+                    node.row.pid,
+                    node.row.cpu_percent,
+                    node.row.rss_kb / 1024
+                ),
+                Style::default().fg(theme.dim),
+            ),
+            Span::styled(
+                format!("{}{}", "  ".repeat(node.depth - 1), node.row.command),
+                style,
+            ),
+        ];
+        if node.is_lsp {
+            spans.push(Span::styled(
+                "  [LSP, excluded from state]",
+                Style::default().fg(theme.orange),
+            ));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    // Keep the scroll position within the content
+    let max_scroll = (lines.len() as u16).saturating_sub(area.height);
+    app.detail_scroll = app.detail_scroll.min(max_scroll);
+    frame.render_widget(Paragraph::new(lines).scroll((app.detail_scroll, 0)), area);
+}
+
+/// Build the normal-mode help bar from the active key bindings, showing the
+/// first chord bound to each action.
+fn help_bar_spans(bindings: &KeyBindings, theme: &Theme, view: View) -> Vec<Span<'static>> {
+    let first = |action: Action| bindings.chords_for(action).into_iter().next();
+    let mut entries: Vec<(String, &'static str)> = Vec::new();
+
+    if view == View::Dashboard {
+        let moves: Vec<String> = [Action::Left, Action::Next, Action::Previous, Action::Right]
+            .into_iter()
+            .filter_map(first)
+            .collect();
+        if !moves.is_empty() {
+            entries.push((moves.join("/"), "move"));
+        }
+        for (action, label) in [
+            (Action::Select, Action::Select.label()),
+            (Action::Dashboard, "list"),
+            (Action::Quit, Action::Quit.label()),
+        ] {
+            if let Some(chord) = first(action) {
+                entries.push((chord, label));
+            }
+        }
+        return help_entries_to_spans(entries, theme);
+    }
+
+    if view == View::Detail {
+        match (first(Action::Next), first(Action::Previous)) {
+            (Some(down), Some(up)) => entries.push((format!("{}/{}", down, up), "scroll")),
+            (Some(down), None) => entries.push((down, "scroll")),
+            (None, Some(up)) => entries.push((up, "scroll")),
+            (None, None) => {}
+        }
+        for (action, label) in [
+            (Action::Select, Action::Select.label()),
+            (Action::Peek, Action::Peek.label()),
+            (Action::KillServer, Action::KillServer.label()),
+            (Action::CopyUrl, Action::CopyUrl.label()),
+            (Action::Details, "back"),
+        ] {
+            if let Some(chord) = first(action) {
+                entries.push((chord, label));
+            }
+        }
+        return help_entries_to_spans(entries, theme);
+    }
+
+    match (first(Action::Next), first(Action::Previous)) {
+        (Some(down), Some(up)) => entries.push((format!("{}/{}", down, up), "nav")),
+        (Some(down), None) => entries.push((down, Action::Next.label())),
+        (None, Some(up)) => entries.push((up, Action::Previous.label())),
+        (None, None) => {}
+    }
+    for action in [
+        Action::Select,
+        Action::Peek,
+        Action::Details,
+        Action::Dashboard,
+        Action::CommandMode,
+        Action::CycleGroup,
+        Action::CycleTheme,
+        Action::Quit,
+    ] {
+        if let Some(chord) = first(action) {
+            entries.push((chord, action.label()));
+        }
+    }
+
+    help_entries_to_spans(entries, theme)
+}
+
+fn help_entries_to_spans(
+    entries: Vec<(String, &'static str)>,
+    theme: &Theme,
+) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    for (i, (chord, label)) in entries.into_iter().enumerate() {
+        let lead = if i == 0 { " " } else { "  " };
+        spans.push(Span::styled(
+            format!("{}{}", lead, chord),
+            Style::default().fg(theme.green),
+        ));
+        spans.push(Span::styled(
+            format!(" {}", label),
+            Style::default().fg(theme.dim),
+        ));
+    }
+    spans
+}
+
+fn create_group_header_item(
+    group: &SessionGroup,
+    sessions: &[AiSession],
+    collapsed: bool,
+    is_selected: bool,
+    theme: &Theme,
+    config: &Config,
+) -> ListItem<'static> {
+    let marker = match (collapsed, config.ascii_symbols) {
+        (true, true) => "[+]",
+        (false, true) => "[-]",
+        (true, false) => "▸",
+        (false, false) => "▾",
+    };
+    let marker_style = if is_selected {
+        Style::default()
+            .fg(theme.green)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(theme.dim)
+    };
+
+    let members = group.sessions.iter().filter_map(|&i| sessions.get(i));
+    let running = members
+        .clone()
+        .filter(|s| s.state == SessionState::Running)
+        .count();
+    let waiting = group.sessions.len() - running;
+    let total_cpu: f64 = members.clone().map(|s| s.cpu_percent).sum();
+    let total_mem: u64 = members.map(|s| s.memory_mb).sum();
+
+    // Show "~" for paths under home, like the session rows do
+    let label = match env::var("HOME") {
+        Ok(home) if group.key.starts_with(&home) => format!("~{}", &group.key[home.len()..]),
+        _ => group.key.clone(),
+    };
+
+    ListItem::new(Line::from(vec![
+        Span::styled(format!(" {} ", marker), marker_style),
+        Span::styled(
+            label,
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled("  ", Style::default()),
+        Span::styled(
+            format!(
+                "{}{}",
+                SessionState::Running.symbol(config.ascii_symbols),
+                running
+            ),
+            Style::default().fg(theme.green),
+        ),
+        Span::styled(" ", Style::default()),
+        Span::styled(
+            format!(
+                "{}{}",
+                SessionState::Waiting.symbol(config.ascii_symbols),
+                waiting
+            ),
+            Style::default().fg(theme.orange),
+        ),
+        Span::styled(" | ", Style::default().fg(theme.dim)),
+        Span::styled(
+            format!("CPU: {:.1}%", total_cpu),
+            Style::default().fg(theme.fg),
+        ),
+        Span::styled(" | ", Style::default().fg(theme.dim)),
+        Span::styled(
+            format!("MEM: {}MB", total_mem),
+            Style::default().fg(theme.fg),
+        ),
+    ]))
+}
+
+fn create_session_list_item(
+    session: &AiSession,
+    idx: usize,
+    is_selected: bool,
+    width: u16,
+    app: &App,
+) -> ListItem<'static> {
+    let theme = &app.theme;
+    let config = &app.config;
+    match &app.layout {
+        ListLayout::Normal => {}
+        ListLayout::Compact => {
+            let columns = [
+                Column::State,
+                Column::Agent,
+                Column::Cpu,
+                Column::Mem,
+                Column::Tmux,
+                Column::Ports,
+                Column::Activity,
+                Column::Cwd,
+            ];
+            return create_column_list_item(
+                session,
+                idx,
+                is_selected,
+                width,
+                app,
+                &[columns.to_vec()],
+            );
+        }
+        ListLayout::Custom(lines) => {
+            return create_column_list_item(session, idx, is_selected, width, app, lines);
+        }
+    }
+
+    // First line: agent type and uptime
+    let state_color = if session.state == SessionState::Running {
+        theme.green
+    } else {
+        theme.orange
+    };
+    let mut line1_spans = vec![selection_prefix(is_selected, theme)];
+    line1_spans.extend(index_spans(session, idx, app));
+    line1_spans.extend(vec![
+        Span::styled(
+            format!("{:<10}", session.agent_type),
+            Style::default().fg(theme.aqua).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(" | ", Style::default().fg(theme.dim)),
+        Span::styled(
+            session.state.symbol(config.ascii_symbols).to_string(),
+            Style::default().fg(state_color),
+        ),
+        Span::styled(" ", Style::default().fg(theme.dim)),
+        Span::styled(
+            format!("PID: {}", session.pid),
+            Style::default().fg(theme.green),
+        ),
+        Span::styled(" | ", Style::default().fg(theme.dim)),
+        Span::styled(" ", Style::default().fg(theme.dim)),
+        Span::styled(
+            format_duration(session.uptime_seconds),
+            Style::default().fg(theme.fg),
+        ),
+        Span::styled(" | ", Style::default().fg(theme.dim)),
+        Span::styled(
+            format!("CPU: {:.1}%", session.cpu_percent),
+            Style::default().fg(state_color),
+        ),
+        Span::styled(" | ", Style::default().fg(theme.dim)),
+        Span::styled(
+            format!("MEM: {}MB", session.memory_mb),
+            memory_style(session, theme, config),
+        ),
+    ]);
+    let line1 = Line::from(line1_spans);
+
+    // Second line: PID and tmux info
+    let mut line2 = if let Some(location) = session.location() {
+        let mut spans = vec![
+            Span::styled("     ", Style::default()),
+            Span::styled(" ", Style::default().fg(theme.blue)),
+            Span::styled(" ", Style::default()),
+            Span::styled(location, Style::default().fg(theme.blue)),
+        ];
+        if let (Some(width), Some(height)) = (session.pane_width, session.pane_height) {
+            spans.push(Span::styled(
+                format!(" [{}x{}]", width, height),
+                Style::default().fg(theme.dim),
+            ));
+        }
+        Line::from(spans)
+    } else {
+        Line::from(vec![
+            Span::styled("     ", Style::default()),
+            Span::styled(" ", Style::default().fg(theme.dim)),
+            Span::styled(" not in tmux", Style::default().fg(theme.dim)),
+        ])
+    };
+    if !session.ports.is_empty() {
+        line2
+            .spans
+            .push(Span::styled(" | ", Style::default().fg(theme.dim)));
+        line2.spans.push(ports_span(&session.ports, theme));
+    }
+    if let Some(activity) = &session.activity {
+        let room = (width as usize).saturating_sub(line2.width() + 4);
+        line2
+            .spans
+            .push(Span::styled(" | ", Style::default().fg(theme.dim)));
+        line2.spans.push(activity_span(activity, room, theme));
+    }
+
+    // Third line: working directory with visual formatting
+    let max_cwd_len = (width as usize).saturating_sub(10);
+    let mut path_spans = vec![Span::styled("     ", Style::default())];
+    path_spans.extend(format_path_visual(&session.working_dir, max_cwd_len, theme));
+    let line3 = Line::from(path_spans);
+
+    // Empty line for spacing
+    let line4 = Line::from("");
+
+    ListItem::new(vec![line1, line2, line3, line4])
+}
+
+fn selection_prefix(is_selected: bool, theme: &Theme) -> Span<'static> {
+    let prefix = if is_selected { " " } else { "  " };
+    let prefix_style = if is_selected {
+        Style::default()
+            .fg(theme.green)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(theme.dim)
+    };
+    Span::styled(prefix, prefix_style)
+}
+
+/// Spans for one column of a session row. `room` is the width left on the line,
+/// used to truncate the working directory. Returns nothing for empty fields.
+fn column_spans(
+    column: Column,
+    session: &AiSession,
+    room: usize,
+    theme: &Theme,
+    config: &Config,
+    data: &ColumnData,
+) -> Vec<Span<'static>> {
+    let state_color = if session.state == SessionState::Running {
+        theme.green
+    } else {
+        theme.orange
+    };
+    match column {
+        Column::State => vec![Span::styled(
+            session.state.symbol(config.ascii_symbols).to_string(),
+            Style::default().fg(state_color),
+        )],
+        Column::Agent => vec![Span::styled(
+            format!("{:<8}", session.agent_type),
+            Style::default().fg(theme.aqua).add_modifier(Modifier::BOLD),
+        )],
+        Column::Name => match &session.name {
+            Some(name) => vec![Span::styled(
+                name.clone(),
+                Style::default().fg(theme.accent),
+            )],
+            None => Vec::new(),
+        },
+        Column::Pid => vec![Span::styled(
+            format!("PID: {}", session.pid),
+            Style::default().fg(theme.green),
+        )],
+        Column::Uptime => vec![Span::styled(
+            format_duration(session.uptime_seconds),
+            Style::default().fg(theme.fg),
+        )],
+        Column::Cpu => vec![Span::styled(
+            format!("CPU: {:.1}%", session.cpu_percent),
+            Style::default().fg(state_color),
+        )],
+        Column::Mem => vec![Span::styled(
+            format!("MEM: {}MB", session.memory_mb),
+            memory_style(session, theme, config),
+        )],
+        Column::Tmux => match session.location() {
+            Some(location) => vec![Span::styled(location, Style::default().fg(theme.blue))],
+            None => vec![Span::styled("not in tmux", Style::default().fg(theme.dim))],
+        },
+        Column::Cwd => format_path_visual(&session.working_dir, room, theme),
+        Column::Git => match data.branches.get(&session.working_dir).cloned().flatten() {
+            Some(branch) => vec![Span::styled(
+                format!(
+                    "{} {}",
+                    if config.ascii_symbols { "git:" } else { "" },
+                    branch
+                )
+                .trim()
+                .to_string(),
+                Style::default().fg(theme.aqua),
+            )],
+            None => Vec::new(),
+        },
+        Column::Tokens => match data.tokens.get(&session.pid) {
+            Some(&tokens) => vec![Span::styled(
+                format!("{} tok", format_tokens(tokens)),
+                Style::default().fg(theme.blue),
+            )],
+            None => Vec::new(),
+        },
+        Column::Ports if session.ports.is_empty() => Vec::new(),
+        Column::Ports => vec![ports_span(&session.ports, theme)],
+        Column::Activity => match &session.activity {
+            Some(activity) => vec![activity_span(activity, room, theme)],
+            None => Vec::new(),
+        },
+    }
+}
+
+fn ports_span(ports: &[ListeningPort], theme: &Theme) -> Span<'static> {
+    Span::styled(
+        ports
+            .iter()
+            .map(|p| p.label())
+            .collect::<Vec<_>>()
+            .join(" "),
+        Style::default().fg(theme.accent),
+    )
+}
+
+/// The activity string, cut to `room` columns.
+fn activity_span(activity: &str, room: usize, theme: &Theme) -> Span<'static> {
+    let text = if activity.chars().count() > room {
+        let mut cut: String = activity.chars().take(room.saturating_sub(3)).collect();
+        cut.push_str("...");
+        cut
+    } else {
+        activity.to_string()
+    };
+    Span::styled(text, Style::default().fg(theme.green))
+}
+
+/// The "[n] " list number, plus a marker on the previously visited session.
+fn index_spans(session: &AiSession, idx: usize, app: &App) -> Vec<Span<'static>> {
+    let theme = &app.theme;
+    let mut spans = vec![
+        Span::styled(format!("[{}] ", idx + 1), Style::default().fg(theme.dim)),
+        Span::styled(
+            format!("{} ", session.id),
+            Style::default().fg(theme.accent),
+        ),
+    ];
+    if app.previous_pane.as_ref().is_some_and(|p| p.holds(session)) {
+        let marker = if app.config.ascii_symbols {
+            "<-"
+        } else {
+            "↩"
+        };
+        spans.push(Span::styled(
+            format!("{} ", marker),
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    spans
+}
+
+/// Render a session as lines of columns (compact and custom layouts).
+fn create_column_list_item(
+    session: &AiSession,
+    idx: usize,
+    is_selected: bool,
+    width: u16,
+    app: &App,
+    lines: &[Vec<Column>],
+) -> ListItem<'static> {
+    let theme = &app.theme;
+    let config = &app.config;
+    let width = width as usize;
+    let items: Vec<Line> = lines
+        .iter()
+        .enumerate()
+        .map(|(line_idx, columns)| {
+            let mut spans = if line_idx == 0 {
+                let mut spans = vec![selection_prefix(is_selected, theme)];
+                spans.extend(index_spans(session, idx, app));
+                spans
+            } else {
+                vec![Span::styled("     ", Style::default())]
+            };
+
+            let mut first = true;
+            for &column in columns {
+                let used: usize = spans.iter().map(|s| s.width()).sum();
+                let room = width.saturating_sub(used + 3);
+                let column_spans =
+                    column_spans(column, session, room, theme, config, &app.column_data);
+                if column_spans.is_empty() {
+                    continue;
+                }
+                if !first {
+                    spans.push(Span::styled(" | ", Style::default().fg(theme.dim)));
+                }
+                first = false;
+                spans.extend(column_spans);
+            }
+            Line::from(spans)
+        })
+        .collect();
+
+    ListItem::new(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::fixtures::fixture_app;
+    use crate::app::fixtures::fixture_sessions;
+    use crate::theme::ThemeName;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Compare `actual` with the snapshot stored in `src/snapshots/<name>.snap`.
+    /// Run with `UPDATE_SNAPSHOTS=1` to write new or changed snapshots.
+    fn assert_snapshot(name: &str, actual: &str) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src")
+            .join("snapshots")
+            .join(format!("{}.snap", name));
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::create_dir_all(path.parent().expect("snapshot dir")).expect("create snapshot dir");
+            fs::write(&path, actual).expect("write snapshot");
+            return;
+        }
+        let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!(
+                "missing snapshot {}; run with UPDATE_SNAPSHOTS=1 to create it",
+                path.display()
+            )
+        });
+        assert!(
+            expected == actual,
+            "snapshot {} differs; run with UPDATE_SNAPSHOTS=1 to update it\n--- expected\n{}\n--- actual\n{}",
+            name,
+            expected,
+            actual
+        );
+    }
+
+    fn render(app: &mut App, width: u16, height: u16) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).expect("test terminal");
+        terminal.draw(|frame| ui(frame, app)).expect("draw");
+        format!("{:?}", terminal.backend().buffer())
+    }
+
+    #[test]
+    fn list_in_every_theme_and_symbol_set() {
+        for theme in ThemeName::all() {
+            for ascii in [false, true] {
+                let mut app = fixture_app(fixture_sessions(), |c| {
+                    c.theme = theme.name().to_string();
+                    c.ascii_symbols = ascii;
+                });
+                let name = format!(
+                    "list_{}_80x24{}",
+                    theme.name(),
+                    if ascii { "_ascii" } else { "" }
+                );
+                assert_snapshot(&name, &render(&mut app, 80, 24));
+            }
+        }
+    }
+
+    #[test]
+    fn list_at_several_sizes() {
+        for (width, height) in [(40, 14), (50, 16), (120, 40)] {
+            for ascii in [false, true] {
+                let mut app = fixture_app(fixture_sessions(), |c| c.ascii_symbols = ascii);
+                let name = format!(
+                    "list_{}x{}{}",
+                    width,
+                    height,
+                    if ascii { "_ascii" } else { "" }
+                );
+                assert_snapshot(&name, &render(&mut app, width, height));
+            }
+        }
+    }
+
+    #[test]
+    fn compact_layout() {
+        for (width, height) in [(50, 16), (120, 40)] {
+            let mut app = fixture_app(fixture_sessions(), |c| c.layout = "compact".to_string());
+            assert_snapshot(
+                &format!("compact_{}x{}", width, height),
+                &render(&mut app, width, height),
+            );
+        }
+    }
+
+    #[test]
+    fn grouped_by_agent() {
+        let mut app = fixture_app(fixture_sessions(), |c| c.group_by = "agent".to_string());
+        assert_snapshot("grouped_agent_80x24", &render(&mut app, 80, 24));
+    }
+
+    #[test]
+    fn monochrome() {
+        let mut app = fixture_app(fixture_sessions(), |c| c.color = "none".to_string());
+        assert_snapshot("list_none_80x24", &render(&mut app, 80, 24));
+    }
+
+    #[test]
+    fn no_sessions() {
+        let mut app = fixture_app(Vec::new(), |_| {});
+        assert_snapshot("empty_50x16", &render(&mut app, 50, 16));
+    }
+}
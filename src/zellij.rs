@@ -0,0 +1,292 @@
+//! zellij integration: mapping processes to panes through the environment
+//! zellij gives pane processes, and focusing panes with `zellij action`.
+
+use crate::backend::Pane;
+use crate::error::{run_command, Error, Result};
+use crate::process::{server_children_env, ProcessRow};
+use crate::session::MultiplexerKind;
+use std::collections::{HashMap, HashSet};
+use std::env;
+
+/// Pane id zellij's CLI uses for the terminal pane numbered `id` in `ZELLIJ_PANE_ID`.
+fn terminal_pane_id(id: &str) -> String {
+    format!("terminal_{}", id)
+}
+
+/// Every pane of every running zellij session, keyed by the PID of the
+/// process running in it: the children of zellij servers, identified by the
+/// `ZELLIJ_SESSION_NAME` and `ZELLIJ_PANE_ID` variables in their environment.
+/// A pane's tab is read from the session's layout (see [`pane_tab`]).
+/// `processes` is the process table to look for servers in. Empty where
+/// there is no `/proc`.
+pub(crate) fn get_zellij_panes(processes: &HashMap<u32, ProcessRow>) -> HashMap<u32, Pane> {
+    let mut panes: HashMap<u32, Pane> = server_children_env(
+        "zellij",
+        &["ZELLIJ_SESSION_NAME", "ZELLIJ_PANE_ID"],
+        processes,
+    )
+    .into_iter()
+    .filter_map(|(pid, mut env)| {
+        let session = env.remove("ZELLIJ_SESSION_NAME")?;
+        let pane = env.remove("ZELLIJ_PANE_ID")?;
+        Some((
+            pid,
+            Pane {
+                multiplexer: MultiplexerKind::Zellij,
+                pane_id: terminal_pane_id(&pane),
+                session,
+                window: None,
+                width: None,
+                height: None,
+                socket: None,
+            },
+        ))
+    })
+    .collect();
+
+    let sessions: HashSet<String> = panes.values().map(|p| p.session.clone()).collect();
+    for session in sessions {
+        let Ok(layout) = run_action(&session, &["dump-layout"]) else {
+            continue;
+        };
+        let tabs = layout_tabs(&layout);
+        for (&pid, pane) in panes.iter_mut().filter(|(_, p)| p.session == session) {
+            pane.window = pane_tab(&tabs, pid, processes);
+        }
+    }
+    panes
+}
+
+/// A tab of a zellij session and the command lines of its panes that run a
+/// command other than the shell, as `zellij action dump-layout` shows them.
+#[derive(Debug, PartialEq)]
+struct LayoutTab {
+    name: String,
+    commands: Vec<String>,
+}
+
+/// The tabs of a layout printed by `zellij action dump-layout`. A pane's
+/// command line is its `command` followed by its `args`, joined by spaces.
+fn layout_tabs(layout: &str) -> Vec<LayoutTab> {
+    let mut tabs: Vec<LayoutTab> = Vec::new();
+    let mut depth = 0;
+    // Depths of the tab being read and of the command pane whose args may follow
+    let mut tab_depth = None;
+    let mut pane_depth = None;
+    for line in layout.lines().map(str::trim) {
+        let is_tab = line == "tab" || line.starts_with("tab ");
+        match tabs.last_mut() {
+            // Tabs inside templates and swap layouts are deeper than the layout's own
+            _ if tab_depth.is_none() && depth == 1 && is_tab => {
+                tabs.push(LayoutTab {
+                    name: kdl_attr(line, "name").unwrap_or_default(),
+                    commands: Vec::new(),
+                });
+                tab_depth = Some(depth);
+            }
+            Some(tab) if tab_depth.is_some() && line.starts_with("pane") => {
+                if let Some(command) = kdl_attr(line, "command") {
+                    tab.commands.push(command);
+                    pane_depth = Some(depth);
+                }
+            }
+            Some(tab) if pane_depth.is_some_and(|d| d + 1 == depth) => {
+                if let (Some(args), Some(command)) =
+                    (line.strip_prefix("args "), tab.commands.last_mut())
+                {
+                    for arg in kdl_strings(args) {
+                        command.push(' ');
+                        command.push_str(&arg);
+                    }
+                }
+            }
+            _ => {}
+        }
+        depth = (depth as i64 + brace_balance(line)).max(0) as usize;
+        if tab_depth.is_some_and(|d| depth <= d) {
+            tab_depth = None;
+        }
+        if pane_depth.is_some_and(|d| depth <= d) {
+            pane_depth = None;
+        }
+    }
+    tabs
+}
+
+/// The quoted strings in a line of KDL, unescaped.
+fn kdl_strings(line: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '"' {
+            continue;
+        }
+        let mut string = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => break,
+                '\\' => string.extend(chars.next()),
+                c => string.push(c),
+            }
+        }
+        strings.push(string);
+    }
+    strings
+}
+
+/// The value of the string property `key` of a KDL node, e.g. `name` in `tab name="dev"`.
+fn kdl_attr(line: &str, key: &str) -> Option<String> {
+    let start = line.find(&format!(" {}=\"", key))?;
+    kdl_strings(&line[start..]).into_iter().next()
+}
+
+/// Braces opened minus braces closed on a line of KDL, outside strings.
+fn brace_balance(line: &str) -> i64 {
+    let mut balance = 0;
+    let mut in_string = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            '{' if !in_string => balance += 1,
+            '}' if !in_string => balance -= 1,
+            _ => {}
+        }
+    }
+    balance
+}
+
+/// The tab of the pane whose process is `pid`: the only tab with a pane
+/// running the command line of that process or of one of its children in
+/// `processes`. None when no tab or several tabs match.
+fn pane_tab(tabs: &[LayoutTab], pid: u32, processes: &HashMap<u32, ProcessRow>) -> Option<String> {
+    let running: Vec<&str> = processes
+        .values()
+        .filter(|row| row.pid == pid || row.ppid == pid)
+        .map(|row| row.command.as_str())
+        .collect();
+    let mut matching = tabs
+        .iter()
+        .filter(|tab| tab.commands.iter().any(|c| running.contains(&c.as_str())));
+    match (matching.next(), matching.next()) {
+        (Some(tab), None) => Some(tab.name.clone()),
+        _ => None,
+    }
+}
+
+/// The zellij session this process runs in.
+pub(crate) fn current_session() -> Option<String> {
+    env::var("ZELLIJ_SESSION_NAME").ok()
+}
+
+/// The zellij pane this process runs in, e.g. "terminal_2".
+pub(crate) fn current_zellij_pane() -> Option<String> {
+    current_session()?;
+    env::var("ZELLIJ_PANE_ID")
+        .ok()
+        .map(|id| terminal_pane_id(&id))
+}
+
+/// Run `zellij action` against `session`, returning its trimmed stdout.
+pub(crate) fn run_action(session: &str, args: &[&str]) -> Result<String> {
+    let mut full = vec!["--session", session, "action"];
+    full.extend_from_slice(args);
+    run_command("zellij", &full)
+}
+
+/// The pane focused by the first client attached to `session`, from
+/// `zellij action list-clients`. None when no client is attached.
+fn focused_pane(session: &str) -> Result<Option<String>> {
+    let clients = run_action(session, &["list-clients"])?;
+    Ok(clients
+        .lines()
+        .skip(1)
+        .find_map(|line| line.split_whitespace().nth(1))
+        .map(str::to_string))
+}
+
+/// Focus `pane_id` in `session`, whose tab is `tab`. zellij's CLI cannot
+/// focus a pane by id, so this switches the attached client to the tab and
+/// moves focus within it until the focused pane is `pane_id`. Fails when no
+/// client is attached to the session or the pane's tab is unknown.
+pub(crate) fn focus_pane(session: &str, tab: Option<&str>, pane_id: &str) -> Result<()> {
+    let Some(focused) = focused_pane(session)? else {
+        return Err(Error::Command {
+            command: format!("zellij --session {} action list-clients", session),
+            message: "no client is attached".to_string(),
+        });
+    };
+    if focused == pane_id {
+        return Ok(());
+    }
+    let Some(tab) = tab else {
+        return Err(Error::Command {
+            command: format!("zellij --session {} action dump-layout", session),
+            message: format!("could not tell which tab pane {} is in", pane_id),
+        });
+    };
+
+    run_action(session, &["go-to-tab-name", tab])?;
+    let mut seen = HashSet::new();
+    while let Some(focused) = focused_pane(session)? {
+        if focused == pane_id {
+            return Ok(());
+        }
+        if !seen.insert(focused) {
+            break;
+        }
+        run_action(session, &["focus-next-pane"])?;
+    }
+    Err(Error::Command {
+        command: format!("zellij --session {} action focus-next-pane", session),
+        message: format!("pane {} not found in tab {}", pane_id, tab),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tabs_and_their_commands_are_read_from_the_layout() {
+        let layout = r#"layout {
+    cwd "/home/me"
+    tab name="Tab #1" focus=true hide_floating_panes=true {
+        pane size=1 borderless=true {
+            plugin location="zellij:tab-bar"
+        }
+        pane split_direction="vertical" {
+            pane command="claude" cwd="src/api" focus=true {
+                args "--resume" "say \"hi\" {"
+                start_suspended true
+            }
+            pane
+        }
+    }
+    tab name="review" {
+        pane command="codex"
+    }
+    swap_tiled_layout name="stacked" {
+        tab min_panes=5 {
+            pane command="htop"
+        }
+    }
+}"#;
+        assert_eq!(
+            layout_tabs(layout),
+            vec![
+                LayoutTab {
+                    name: "Tab #1".to_string(),
+                    commands: vec!["claude --resume say \"hi\" {".to_string()],
+                },
+                LayoutTab {
+                    name: "review".to_string(),
+                    commands: vec!["codex".to_string()],
+                },
+            ]
+        );
+    }
+}
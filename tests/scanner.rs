@@ -0,0 +1,363 @@
+use rpai::{
+    AiSession, Config, FakeMultiplexer, FakeProcesses, MultiplexerKind, Scanner, SessionState,
+};
+use std::time::Duration;
+
+/// A login shell in pane %1 of session "work".
+fn shell() -> FakeProcesses {
+    FakeProcesses::new()
+        .process(1, 0, "init", "/sbin/init")
+        .process(100, 1, "zsh", "-zsh")
+}
+
+fn panes() -> FakeMultiplexer {
+    FakeMultiplexer::new().pane(100, "%1", "work", 2)
+}
+
+fn detect(processes: FakeProcesses) -> Vec<AiSession> {
+    Scanner::new(Config::default())
+        .with_process_source(processes)
+        .with_multiplexer(panes())
+        .scan()
+        .expect("fake backends do not fail")
+}
+
+fn agents(sessions: &[AiSession]) -> Vec<(u32, &str)> {
+    sessions
+        .iter()
+        .map(|s| (s.pid, s.agent_type.as_str()))
+        .collect()
+}
+
+#[test]
+fn matches_agents_by_executable_name_or_argv0() {
+    let sessions = detect(
+        shell()
+            .process(200, 100, "claude", "claude --resume")
+            .process(201, 100, "node", "/usr/local/bin/codex exec")
+            .process(202, 100, "opencode", "opencode")
+            .process(203, 100, "Gemini", "gemini")
+            .process(204, 100, "cursor-agent", "cursor-agent"),
+    );
+
+    assert_eq!(
+        agents(&sessions),
+        vec![
+            (200, "claude"),
+            (201, "codex"),
+            (202, "opencode"),
+            (203, "gemini"),
+            (204, "cursor"),
+        ]
+    );
+}
+
+#[test]
+fn ignores_agent_names_in_arguments() {
+    let sessions = detect(
+        shell()
+            .process(200, 100, "vim", "vim CLAUDE.md")
+            .process(201, 100, "tail", "tail -f /home/me/.codex/log")
+            .process(202, 100, "bash", "bash"),
+    );
+
+    assert!(sessions.is_empty());
+}
+
+#[test]
+fn argv0_takes_precedence_over_executable_name() {
+    // A wrapper named after one agent exec'ing another keeps argv[0]'s name
+    let sessions = detect(shell().process(200, 100, "claude", "opencode run"));
+
+    assert_eq!(agents(&sessions), vec![(200, "opencode")]);
+}
+
+#[test]
+fn skips_cursoruiviewservice() {
+    let sessions = detect(
+        shell()
+            .process(
+                200,
+                1,
+                "CursorUIViewService",
+                "/System/Library/CursorUIViewService",
+            )
+            .process(201, 1, "cursoruiviewservice", "cursoruiviewservice")
+            .process(202, 100, "cursor", "cursor ."),
+    );
+
+    assert_eq!(agents(&sessions), vec![(202, "cursor")]);
+}
+
+#[test]
+fn folds_agent_subprocesses_into_their_parent() {
+    let sessions = detect(
+        shell()
+            .process(200, 100, "claude", "claude")
+            .process(201, 200, "claude", "claude mcp serve")
+            .process(202, 201, "claude", "claude --print"),
+    );
+
+    assert_eq!(agents(&sessions), vec![(200, "claude")]);
+}
+
+#[test]
+fn keeps_agents_started_through_a_shell() {
+    // Only a direct agent parent folds a process; one behind a shell is its own session
+    let sessions = detect(
+        shell()
+            .process(200, 100, "claude", "claude")
+            .process(201, 200, "bash", "/bin/bash -c codex")
+            .process(202, 201, "codex", "codex"),
+    );
+
+    assert_eq!(agents(&sessions), vec![(200, "claude"), (202, "codex")]);
+}
+
+#[test]
+fn nested_agents_keep_their_own_cpu_figure() {
+    // codex is in claude's tree and its own; sampling it twice in one scan
+    // would measure it over no time at all
+    let sessions = detect(
+        shell()
+            .process(200, 100, "claude", "claude")
+            .process(201, 200, "bash", "/bin/bash -c codex")
+            .process(202, 201, "codex", "codex")
+            .with_cpu(202, 40.0)
+            .with_cpu_time(202, Duration::from_secs(30)),
+    );
+
+    let codex = sessions.iter().find(|s| s.pid == 202).unwrap();
+    assert_eq!(codex.cpu_percent, 40.0);
+    assert_eq!(codex.state, SessionState::Running);
+}
+
+#[test]
+fn maps_agents_to_the_pane_of_their_nearest_ancestor() {
+    let sessions = detect(
+        shell()
+            .process(150, 100, "make", "make agent")
+            .process(200, 150, "claude", "claude"),
+    );
+
+    let session = &sessions[0];
+    assert_eq!(session.pane_id.as_deref(), Some("%1"));
+    assert_eq!(session.multiplexer, Some(MultiplexerKind::Tmux));
+    assert_eq!(session.mux_session.as_deref(), Some("work"));
+    assert_eq!(session.mux_window.as_deref(), Some("2"));
+    assert_eq!(
+        (session.pane_width, session.pane_height),
+        (Some(80), Some(24))
+    );
+}
+
+/// An agent `depth` processes below the pane's shell (1 = its direct child).
+fn nested_agent(depth: u32) -> FakeProcesses {
+    let mut processes = shell();
+    let mut parent = 100;
+    for pid in 1000..1000 + depth - 1 {
+        processes = processes.process(pid, parent, "sh", "sh -c next");
+        parent = pid;
+    }
+    processes.process(200, parent, "claude", "claude")
+}
+
+#[test]
+fn pane_ancestry_walk_stops_after_25_steps() {
+    // The walk visits the agent and up to 24 of its ancestors
+    let found = detect(nested_agent(24));
+    assert_eq!(found[0].pane_id.as_deref(), Some("%1"));
+
+    let too_deep = detect(nested_agent(25));
+    assert_eq!(too_deep[0].pane_id, None);
+}
+
+#[test]
+fn maps_agents_to_zellij_panes() {
+    let sessions = Scanner::new(Config::default())
+        .with_process_source(
+            shell()
+                .process(300, 1, "zellij", "zellij --server /run/zellij/dev")
+                .process(301, 300, "bash", "bash")
+                .process(302, 301, "codex", "codex"),
+        )
+        .with_multiplexer(panes().zellij_pane(301, "terminal_2", "dev"))
+        .scan()
+        .expect("fake backends do not fail");
+
+    let session = &sessions[0];
+    assert_eq!(session.multiplexer, Some(MultiplexerKind::Zellij));
+    assert_eq!(session.pane_id.as_deref(), Some("terminal_2"));
+    assert_eq!(session.mux_window, None);
+    assert_eq!(session.location().as_deref(), Some("zellij dev terminal_2"));
+}
+
+#[test]
+fn maps_agents_to_screen_windows_through_their_ancestors() {
+    let sessions = Scanner::new(Config::default())
+        .with_process_source(
+            shell()
+                .process(400, 1, "screen", "SCREEN -S build")
+                .process(401, 400, "bash", "/bin/bash")
+                .process(402, 401, "claude", "claude"),
+        )
+        .with_multiplexer(panes().screen_window(401, "4000.build", 3))
+        .scan()
+        .expect("fake backends do not fail");
+
+    let session = &sessions[0];
+    assert_eq!(session.multiplexer, Some(MultiplexerKind::Screen));
+    assert_eq!(session.mux_session.as_deref(), Some("4000.build"));
+    assert_eq!(session.mux_window.as_deref(), Some("3"));
+    assert_eq!(session.location().as_deref(), Some("4000.build:3"));
+}
+
+#[test]
+fn agents_outside_any_pane_have_no_location() {
+    let sessions = detect(shell().process(200, 1, "claude", "claude"));
+
+    assert_eq!(sessions[0].pane_id, None);
+    assert_eq!(sessions[0].mux_session, None);
+}
+
+#[test]
+fn lsp_servers_do_not_make_an_agent_running() {
+    let sessions = detect(
+        shell()
+            .process(200, 100, "claude", "claude")
+            .with_cpu(200, 1.0)
+            .process(
+                201,
+                200,
+                "node",
+                "node /usr/lib/node_modules/pyright/pyright-langserver --stdio",
+            )
+            .with_cpu(201, 80.0)
+            .process(202, 200, "rust-analyzer", "rust-analyzer")
+            .with_cpu(202, 40.0),
+    );
+
+    assert_eq!(sessions[0].state, SessionState::Waiting);
+    assert_eq!(sessions[0].cpu_percent, 1.0);
+}
+
+#[test]
+fn busy_descendants_make_an_agent_running() {
+    let sessions = detect(
+        shell()
+            .process(200, 100, "claude", "claude")
+            .process(201, 200, "bash", "/bin/bash -c cargo test")
+            .process(202, 201, "cargo", "cargo test")
+            .with_cpu(202, 95.0)
+            .with_elapsed(202, 120),
+    );
+
+    assert_eq!(sessions[0].state, SessionState::Running);
+    assert_eq!(sessions[0].cpu_percent, 95.0);
+    assert_eq!(
+        sessions[0].activity.as_deref(),
+        Some("running: cargo test (2m)")
+    );
+}
+
+#[test]
+fn lsp_memory_is_reported_separately() {
+    let sessions = detect(
+        shell()
+            .process(200, 100, "claude", "claude")
+            .with_rss_kb(200, 300 * 1024)
+            .process(201, 200, "node", "node vite")
+            .with_rss_kb(201, 200 * 1024)
+            .process(202, 200, "gopls", "gopls serve")
+            .with_rss_kb(202, 500 * 1024),
+    );
+
+    assert_eq!(sessions[0].memory_mb, 500);
+    assert_eq!(sessions[0].lsp_memory_mb, 500);
+}
+
+#[test]
+fn pss_is_read_for_the_details_only() {
+    let scanner = Scanner::new(Config::default())
+        .with_process_source(
+            shell()
+                .process(200, 100, "claude", "claude")
+                .with_pss_kb(200, 300 * 1024)
+                .process(201, 200, "node", "node vite")
+                .with_pss_kb(201, 100 * 1024)
+                .process(202, 200, "gopls", "gopls serve"),
+        )
+        .with_multiplexer(panes());
+    scanner.scan().expect("fake backends do not fail");
+
+    let details = scanner.details(200).expect("details of the scanned agent");
+    assert_eq!(details.pss_mb, Some(400));
+}
+
+#[test]
+fn reports_listening_ports_in_the_agent_tree() {
+    let sessions = detect(
+        shell()
+            .process(200, 100, "claude", "claude")
+            .with_cwd(200, "/src/web")
+            .process(201, 200, "node", "node /src/web/node_modules/.bin/vite")
+            .with_listening_port(201, 5173)
+            .process(300, 100, "python3", "python3 -m http.server")
+            .with_listening_port(300, 8000),
+    );
+
+    assert_eq!(sessions[0].working_dir, "/src/web");
+    let ports: Vec<String> = sessions[0].ports.iter().map(|p| p.label()).collect();
+    assert_eq!(ports, vec![":5173 (vite)"]);
+}
+
+#[test]
+fn details_reuse_the_last_scan() {
+    let scanner = Scanner::new(Config::default())
+        .with_process_source(
+            shell()
+                .process(200, 100, "claude", "claude")
+                .process(201, 200, "bash", "bash -c cargo test")
+                .process(202, 201, "cargo", "cargo test"),
+        )
+        .with_multiplexer(panes());
+    scanner.scan().expect("fake backends do not fail");
+
+    let details = scanner.details(200).expect("details of the scanned agent");
+    let ancestry: Vec<u32> = details.ancestry.iter().map(|r| r.pid).collect();
+    assert_eq!(ancestry, vec![200, 100]);
+    assert_eq!(details.pane.map(|p| p.pane_id), Some("%1".to_string()));
+    let tree: Vec<(usize, u32)> = details
+        .descendants
+        .iter()
+        .map(|n| (n.depth, n.row.pid))
+        .collect();
+    assert_eq!(tree, vec![(1, 201), (2, 202)]);
+}
+
+#[test]
+fn jumping_to_an_agent_outside_tmux_is_an_error() {
+    let sessions = detect(shell().process(200, 1, "claude", "claude"));
+    let multiplexer = panes();
+
+    let result = rpai::jump_to_session_with(&multiplexer, &sessions[0], false);
+
+    assert!(matches!(result, Err(rpai::Error::NotInMultiplexer)));
+    assert!(multiplexer.calls().is_empty());
+}
+
+#[test]
+fn jumps_through_the_multiplexer_without_touching_the_config_dir() {
+    let home = std::env::temp_dir().join(format!("rpai-jump-{}", std::process::id()));
+    std::env::set_var("HOME", &home);
+    let sessions = detect(shell().process(200, 100, "claude", "claude"));
+    let multiplexer = panes().with_current_pane("%9");
+
+    rpai::jump_to_session_with(&multiplexer, &sessions[0], true).expect("fake jump succeeds");
+
+    assert_eq!(
+        multiplexer.calls(),
+        vec!["focus work:2.%1 zoom", "attach work:2.%1"]
+    );
+    assert!(!home.exists(), "the library recorded the visit");
+}